@@ -1,3 +1,5 @@
+use ascii_renderer::{Format, cli::RenderArgs};
+use image::ImageFormat;
 use clap::Parser;
 use std::env::current_dir;
 use std::fs::{self, create_dir_all, remove_dir_all};
@@ -6,10 +8,6 @@ use std::process::Command;
 use std::thread::sleep;
 use std::time::Duration;
 
-mod image_processor;
-use crate::image_processor::resize_image;
-use image_processor::ascii_representation_from_image;
-
 #[derive(Parser)]
 struct Cli {
     #[clap(name = "path", help = "Path to the input file (image or video)")]
@@ -18,23 +16,14 @@ struct Cli {
     #[clap(long, default_value = "true", help = "Process a video file as an animation")]
     animation: bool,
 
-    #[clap(short, long, help = "Use grayscale ASCII characters instead of colored output")]
-    gray_scale: bool,
-
-    #[clap(short, long, default_value = "20", help = "Width of the ASCII output")]
-    width: u32,
-
-    #[clap(long, default_value = "20", help = "Height of the ASCII output")]
-    height: u32,
-
     #[clap(short, long, help = "Output directory for the ASCII text frames")]
     output: Option<String>,
 
     #[clap(long, help = "Save the resized image used for ASCII conversion")]
     save_intermediate: Option<String>,
 
-    #[clap(short, long, help = "Adjust the width scaling factor")]
-    fatness: Option<f32>,
+    #[clap(flatten)]
+    render: RenderArgs,
 
     #[clap(
         long,
@@ -110,6 +99,10 @@ fn play_animation<P: AsRef<Path>>(
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
+    if cli.play && cli.render.format != Format::Ansi {
+        return Err("--play only works with --format ansi".into());
+    }
+
     let animations_path = current_dir()?.join("animations");
     let frames_path = current_dir()?.join("frames");
     let text_path = if let Some(output_dir) = &cli.output {
@@ -137,18 +130,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         frame_files.sort();
 
+        // Leave the last row for the cursor during playback when fitting the terminal
+        let renderer = cli.render.renderer(1)?;
+
         for (index, frame_path) in frame_files.iter().enumerate() {
             let image = image::open(frame_path)?;
-            let resized_image = resize_image(&image, cli.width, cli.height, cli.fatness);
+            let resized_image = renderer.resize(&image);
 
-            if let Some(save_path) = &cli.save_intermediate {
-                if index == 0 {
-                    resized_image.save(save_path)?;
-                }
+            if let (Some(save_path), 0) = (&cli.save_intermediate, index) {
+                resized_image.save(save_path)?;
             }
 
-            let ascii_frame = ascii_representation_from_image(&resized_image, cli.gray_scale);
-            let text_file_path = text_path.join(format!("frame_{:04}.txt", index));
+            let text_file_path = text_path.join(format!("frame_{:04}.{}", index, cli.render.format.extension()));
+            let ascii_frame = match cli.render.format {
+                Format::Ansi => renderer.render(&resized_image),
+                Format::Html => renderer.render_html(&resized_image),
+                Format::Svg => renderer.render_svg(&resized_image),
+                Format::Png => {
+                    renderer
+                        .render_png(&resized_image)
+                        .save_with_format(&text_file_path, ImageFormat::Png)?;
+                    continue;
+                }
+            };
             fs::write(&text_file_path, ascii_frame.as_bytes())?;
         }
 
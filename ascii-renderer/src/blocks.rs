@@ -0,0 +1,181 @@
+use image::{DynamicImage, GenericImageView, Rgb, Rgba};
+
+use crate::{Cell, is_visible};
+
+/// Pixels per half-block character: the top and bottom half of a character cell.
+pub const HALFBLOCK_SIZE: (u32, u32) = (1, 2);
+
+/// Pixels per quadrant character: a 2x2 block.
+pub const QUADRANT_SIZE: (u32, u32) = (2, 2);
+
+/// Quadrant characters indexed by the quadrants they fill.
+///
+/// Bit 0 is the top-left quadrant, bit 1 top-right, bit 2 bottom-left and
+/// bit 3 bottom-right.
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Converts an image into half blocks, one character per two pixels stacked vertically.
+///
+/// Each character is an upper half block (`▀`) colored with the top pixel,
+/// on a background colored with the bottom pixel. Transparent halves keep the
+/// terminal's background.
+///
+/// # Arguments
+/// * `image` - The resized image
+///
+/// # Returns
+/// * `Vec<Vec<Cell>>` - One row of cells per two rows of pixels
+pub fn halfblock_cells(image: &DynamicImage) -> Vec<Vec<Cell>> {
+    let (width, height) = image.dimensions();
+    let rows = height.div_ceil(HALFBLOCK_SIZE.1);
+
+    (0..rows)
+        .map(|row| {
+            (0..width)
+                .map(|x| {
+                    let top = visible_color(image, x, row * 2);
+                    let bottom = visible_color(image, x, row * 2 + 1);
+                    match (top, bottom) {
+                        (None, None) => Cell::BLANK,
+                        // Only the lower half is drawn, so that one is the foreground
+                        (None, Some(bottom)) => Cell {
+                            ch: '▄',
+                            foreground: Some(bottom),
+                            background: None,
+                        },
+                        (Some(top), bottom) => Cell {
+                            ch: '▀',
+                            foreground: Some(top),
+                            background: bottom,
+                        },
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Converts an image into quadrant blocks, one character per 2x2 block of pixels.
+///
+/// The four pixels are split into two groups, drawn as the foreground and
+/// background of a quadrant character. Of all splits, the one whose group
+/// averages are closest to the pixels is used. When some pixels are
+/// transparent, the visible ones are drawn and the rest keep the terminal's
+/// background.
+///
+/// # Arguments
+/// * `image` - The resized image
+///
+/// # Returns
+/// * `Vec<Vec<Cell>>` - One row of cells per two rows of pixels
+pub fn quadrant_cells(image: &DynamicImage) -> Vec<Vec<Cell>> {
+    let (width, height) = image.dimensions();
+    let (cell_width, cell_height) = QUADRANT_SIZE;
+    let columns = width.div_ceil(cell_width);
+    let rows = height.div_ceil(cell_height);
+
+    (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    // Pixels in the order of the mask bits
+                    let block: [Option<Rgb<u8>>; 4] = std::array::from_fn(|i| {
+                        let x = column * cell_width + i as u32 % 2;
+                        let y = row * cell_height + i as u32 / 2;
+                        visible_color(image, x, y)
+                    });
+                    quadrant(block)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Picks the quadrant character and colors for one 2x2 block.
+fn quadrant(block: [Option<Rgb<u8>>; 4]) -> Cell {
+    let visible: u8 = (0..4).filter(|&i| block[i].is_some()).map(|i| 1 << i).sum();
+    if visible == 0 {
+        return Cell::BLANK;
+    }
+    if visible != 0b1111 {
+        return Cell {
+            ch: QUADRANTS[visible as usize],
+            foreground: Some(average(&block, visible)),
+            background: None,
+        };
+    }
+
+    let pixels = block.map(|pixel| pixel.unwrap_or(Rgb([0, 0, 0])));
+    let (mask, foreground, background) = (1..=15u8)
+        .map(|mask| {
+            let foreground = average(&block, mask);
+            let background = average(&block, !mask & 0b1111);
+            let error: u32 = (0..4)
+                .map(|i| {
+                    let fit = if mask & (1 << i) != 0 {
+                        foreground
+                    } else {
+                        background
+                    };
+                    distance_squared(pixels[i], fit)
+                })
+                .sum();
+            (error, mask, foreground, background)
+        })
+        .min_by_key(|&(error, ..)| error)
+        .map(|(_, mask, foreground, background)| (mask, foreground, background))
+        .unwrap();
+
+    Cell {
+        ch: QUADRANTS[mask as usize],
+        foreground: Some(foreground),
+        // A full block hides its background
+        background: (mask != 0b1111).then_some(background),
+    }
+}
+
+/// Average color of the pixels selected by `mask`; black if none are selected.
+fn average(block: &[Option<Rgb<u8>>; 4], mask: u8) -> Rgb<u8> {
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    for (i, pixel) in block.iter().enumerate() {
+        if let Some(Rgb(pixel)) = pixel.filter(|_| mask & (1 << i) != 0) {
+            for channel in 0..3 {
+                sum[channel] += pixel[channel] as u32;
+            }
+            count += 1;
+        }
+    }
+    Rgb(sum.map(|channel| (channel / count.max(1)) as u8))
+}
+
+/// Squared Euclidean distance between two RGB colors.
+fn distance_squared(a: Rgb<u8>, b: Rgb<u8>) -> u32 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(x, y)| (*x as i32 - *y as i32).pow(2) as u32)
+        .sum()
+}
+
+/// The color of the pixel at `(x, y)`, or `None` if it is transparent or outside the image.
+fn visible_color(image: &DynamicImage, x: u32, y: u32) -> Option<Rgb<u8>> {
+    if x >= image.width() || y >= image.height() {
+        return None;
+    }
+    let rgba: Rgba<u8> = image.get_pixel(x, y);
+    let [r, g, b, _] = rgba.0;
+    is_visible(rgba).then_some(Rgb([r, g, b]))
+}
+
+/// The quadrants a block character fills, as a mask like in [`QUADRANTS`].
+///
+/// # Returns
+/// * `Option<u8>` - The mask, or `None` if `ch` is not one of the quadrant characters
+pub(crate) fn quadrants(ch: char) -> Option<u8> {
+    QUADRANTS
+        .iter()
+        .position(|&quadrant| quadrant == ch && ch != ' ')
+        .map(|mask| mask as u8)
+}
@@ -0,0 +1,147 @@
+use image::{DynamicImage, GenericImageView, Rgb};
+
+use crate::{Cell, is_visible, ramp::luminance};
+
+/// Pixels per braille character: two columns of four dots.
+pub const CELL_SIZE: (u32, u32) = (2, 4);
+
+/// The braille pattern with no dots raised (U+2800); dots are added as bits.
+const BLANK: u32 = 0x2800;
+
+/// Bit of each dot in a braille character, indexed by `[row][column]`.
+///
+/// The first six dots were numbered down the left column and then the right;
+/// dots 7 and 8 were added below later, hence the jump in the last row.
+const DOT_BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Converts an image into braille characters, one per 2x4 block of pixels.
+///
+/// A dot is raised where the pixel is at least `threshold` bright. With
+/// `dither`, each pixel's rounding error is spread to its unvisited
+/// neighbours (Floyd-Steinberg), so gradients turn into dot densities instead
+/// of hard edges. A character is colored with the average color of its
+/// raised dots.
+///
+/// # Arguments
+/// * `image` - The resized image
+/// * `threshold` - Luminance (0.0-1.0) from which a dot is raised
+/// * `dither` - Whether to dither instead of thresholding each pixel on its own
+///
+/// # Returns
+/// * `Vec<Vec<Cell>>` - One row of cells per four rows of pixels
+pub fn cells(image: &DynamicImage, threshold: f32, dither: bool) -> Vec<Vec<Cell>> {
+    let (width, height) = image.dimensions();
+    let lit = raised_dots(image, threshold, dither);
+
+    let (cell_width, cell_height) = CELL_SIZE;
+    let columns = width.div_ceil(cell_width);
+    let rows = height.div_ceil(cell_height);
+
+    (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| {
+                    let mut bits = 0;
+                    let mut sum = [0u32; 3];
+                    let mut count = 0;
+                    for (dy, dot_row) in DOT_BITS.iter().enumerate() {
+                        for (dx, bit) in dot_row.iter().enumerate() {
+                            let x = column * cell_width + dx as u32;
+                            let y = row * cell_height + dy as u32;
+                            if x >= width || y >= height || !lit[y as usize][x as usize] {
+                                continue;
+                            }
+                            bits |= bit;
+                            let pixel = image.get_pixel(x, y).0;
+                            for channel in 0..3 {
+                                sum[channel] += pixel[channel] as u32;
+                            }
+                            count += 1;
+                        }
+                    }
+
+                    if count == 0 {
+                        return Cell {
+                            ch: char::from_u32(BLANK).unwrap_or(' '),
+                            foreground: None,
+                            background: None,
+                        };
+                    }
+                    Cell {
+                        ch: char::from_u32(BLANK | bits).unwrap_or(' '),
+                        foreground: Some(Rgb(sum.map(|channel| (channel / count) as u8))),
+                        background: None,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Decides for every pixel whether its dot is raised.
+///
+/// # Arguments
+/// * `image` - The resized image
+/// * `threshold` - Luminance (0.0-1.0) from which a dot is raised
+/// * `dither` - Whether to apply Floyd-Steinberg dithering
+///
+/// # Returns
+/// * `Vec<Vec<bool>>` - Rows of raised (true) and lowered (false) dots
+fn raised_dots(image: &DynamicImage, threshold: f32, dither: bool) -> Vec<Vec<bool>> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut levels: Vec<Vec<f32>> = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| luminance(image.get_pixel(x as u32, y as u32)))
+                .collect()
+        })
+        .collect();
+    let mut lit = vec![vec![false; width]; height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let level = levels[y][x];
+            let raised = level >= threshold;
+            // Transparent pixels are never drawn, but still take part in the dithering
+            lit[y][x] = raised && is_visible(image.get_pixel(x as u32, y as u32));
+            if !dither {
+                continue;
+            }
+
+            let error = level - if raised { 1.0 } else { 0.0 };
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let Some(x) = x.checked_add_signed(dx).filter(|&x| x < width) else {
+                    return;
+                };
+                if let Some(row) = levels.get_mut(y + dy) {
+                    row[x] += error * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    lit
+}
+
+/// The raised dots of a braille character.
+///
+/// # Returns
+/// * `Option<Vec<(u32, u32)>>` - Column and row of each raised dot, or `None`
+///   if `ch` is not a braille pattern
+pub(crate) fn raised(ch: char) -> Option<Vec<(u32, u32)>> {
+    let bits = (ch as u32)
+        .checked_sub(BLANK)
+        .filter(|&bits| bits <= 0xff)?;
+    let mut dots = Vec::new();
+    for (row, dot_row) in DOT_BITS.iter().enumerate() {
+        for (column, bit) in dot_row.iter().enumerate() {
+            if bits & bit != 0 {
+                dots.push((column as u32, row as u32));
+            }
+        }
+    }
+    Some(dots)
+}
@@ -0,0 +1,169 @@
+//! Command-line options shared by `image-to-ascii` and `ascii-animations`.
+//!
+//! Both tools flatten [`RenderArgs`] into their own clap parser and build
+//! their renderer with [`RenderArgs::renderer`], so a new rendering option
+//! only has to be added here.
+
+use clap::Args;
+use image::Rgb;
+
+use crate::color::parse_hex;
+use crate::ramp::DEFAULT_RAMP;
+use crate::{
+    AsciiRenderer, ColorDepth, DEFAULT_CELL_ASPECT, DEFAULT_FATNESS, Distance, Fit, Format, Mode,
+};
+
+/// Options that control how images are rendered.
+#[derive(Args, Debug, Clone)]
+// Keep the doc comment above out of the tools' help
+#[command(about = None, long_about = None)]
+pub struct RenderArgs {
+    /// When enabled, outputs the ASCII art in grayscale without color
+    #[clap(short, long)]
+    pub gray_scale: bool,
+
+    /// Colors the terminal can show: "truecolor", "256", "16" or "none"
+    /// Detected from the COLORTERM and TERM environment variables if not given
+    #[clap(long)]
+    pub color_depth: Option<ColorDepth>,
+
+    /// How much (0-255 per channel) a color may differ from the previous character's
+    /// and still be drawn with it; higher values make the output smaller
+    #[clap(long, default_value = "0")]
+    pub color_tolerance: u8,
+
+    /// Width of the output ASCII art in characters
+    /// Defaults to 20 characters (times the fatness); given alone, the height follows the image's aspect ratio
+    #[clap(short, long)]
+    pub width: Option<u32>,
+
+    /// Height of the output ASCII art in characters
+    /// Defaults to 20 characters
+    #[clap(long)]
+    pub height: Option<u32>,
+
+    /// Size the output to the terminal, keeping the image's aspect ratio
+    #[clap(long, value_parser = ["terminal"], conflicts_with_all = ["width", "height"])]
+    pub fit: Option<String>,
+
+    /// Height of a character cell relative to its width, used to keep the aspect ratio
+    /// with --fit or --width alone
+    #[clap(long, default_value_t = DEFAULT_CELL_ASPECT)]
+    pub cell_aspect: f32,
+
+    /// Scaling factor for character width compensation, when both or neither of
+    /// --width and --height are given
+    /// Helps adjust for the fact that ASCII characters are typically taller than wide
+    /// Default value is 2.45 if not specified
+    #[clap(short, long, alias = "fattness")]
+    pub fatness: Option<f32>,
+
+    /// How characters are chosen: "edges" picks shapes from the neighbouring pixels,
+    /// "ramp" picks by brightness, "hybrid" uses shapes for contours and brightness for flat areas,
+    /// "braille" draws 2x4 pixels per character as braille dots, "halfblock" draws two pixels per
+    /// character with foreground and background colors, "quadrant" draws 2x2 pixels in two colors
+    #[clap(short, long, default_value = "edges")]
+    pub mode: Mode,
+
+    /// How the edges and hybrid modes compare neighbouring colors: "rgba" (squared distance
+    /// of the values) or "lab" (perceived difference, CIELAB ΔE)
+    #[clap(long, default_value = "rgba")]
+    pub distance: Distance,
+
+    /// How different neighbouring colors may be and still count as the same area
+    /// Defaults to 50 for the rgba distance and 2.3 for lab
+    #[clap(long)]
+    pub threshold: Option<f32>,
+
+    /// Characters used by the ramp and hybrid modes, from darkest to brightest
+    #[clap(long, default_value = DEFAULT_RAMP)]
+    pub ramp: String,
+
+    /// Brightness (0.0-1.0) from which a braille dot is drawn
+    #[clap(long, default_value = "0.5")]
+    pub brightness_threshold: f32,
+
+    /// Use Floyd-Steinberg dithering for braille dots, which keeps gradients visible
+    #[clap(long)]
+    pub dither: bool,
+
+    /// Output format: "ansi" for terminals, "html" for a standalone web page,
+    /// "svg" or "png" for an image of the text
+    #[clap(long, default_value = "ansi")]
+    pub format: Format,
+
+    /// Size of the text in SVG and PNG output, in pixels
+    #[clap(long, default_value = "16")]
+    pub font_size: f32,
+
+    /// CSS font family of the HTML output
+    #[clap(long, default_value = "monospace")]
+    pub font: String,
+
+    /// Background color of the HTML, SVG and PNG output, as a hex color like "#1e1e1e"
+    #[clap(long, default_value = "#000000", value_parser = parse_hex)]
+    pub background: Rgb<u8>,
+}
+
+impl RenderArgs {
+    /// Builds the renderer the options describe.
+    ///
+    /// # Arguments
+    /// * `reserved_rows` - Terminal rows to leave free below the art with `--fit terminal`,
+    ///   e.g. for the prompt
+    ///
+    /// # Returns
+    /// * `Ok(AsciiRenderer)` - The configured renderer
+    /// * `Err(String)` - `--fit terminal` was given but the tool is not run in a terminal,
+    ///   or the halfblock mode was asked for without colors
+    pub fn renderer(&self, reserved_rows: u32) -> Result<AsciiRenderer, String> {
+        let (width, height, fit) = self.size(reserved_rows)?;
+        let color_depth = self.color_depth.unwrap_or_else(ColorDepth::detect);
+        // Without colors every halfblock character is the same block
+        let plain =
+            self.gray_scale || (self.format == Format::Ansi && color_depth == ColorDepth::None);
+        if self.mode == Mode::Halfblock && plain {
+            return Err("--mode halfblock needs colors; use another mode with --gray-scale or --color-depth none".to_string());
+        }
+
+        let mut builder = AsciiRenderer::builder()
+            .width(width)
+            .height(height)
+            .fit(fit)
+            .cell_aspect(self.cell_aspect)
+            .fatness(self.fatness.unwrap_or(DEFAULT_FATNESS))
+            .gray_scale(self.gray_scale)
+            .color_depth(color_depth)
+            .color_tolerance(self.color_tolerance)
+            .mode(self.mode)
+            .distance(self.distance)
+            .ramp(&self.ramp)
+            .brightness_threshold(self.brightness_threshold)
+            .dither(self.dither)
+            .font(&self.font)
+            .background(self.background)
+            .font_size(self.font_size);
+        // Without --threshold the renderer uses the distance's own default
+        if let Some(threshold) = self.threshold {
+            builder = builder.threshold(threshold);
+        }
+        Ok(builder.build())
+    }
+
+    /// Picks the output size: the terminal's, a width alone, or both dimensions as given.
+    fn size(&self, reserved_rows: u32) -> Result<(u32, u32, Fit), String> {
+        Ok(match (&self.fit, self.width, self.height) {
+            (Some(_), _, _) => {
+                let (columns, rows) =
+                    crate::terminal_size().ok_or("--fit terminal needs to run in a terminal")?;
+                (
+                    columns,
+                    rows.saturating_sub(reserved_rows).max(1),
+                    Fit::Within,
+                )
+            }
+            (None, Some(width), None) => (width, 0, Fit::Width),
+            (None, width, height) => (width.unwrap_or(20), height.unwrap_or(20), Fit::Stretch),
+        })
+    }
+}
@@ -0,0 +1,240 @@
+use image::Rgb;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// How many colors the terminal can show, and so which escape codes are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    /// 24-bit colors (`\x1b[38;2;r;g;bm`)
+    #[default]
+    TrueColor,
+    /// The xterm 256-color palette (`\x1b[38;5;nm`)
+    Ansi256,
+    /// The 16 basic ANSI colors (`\x1b[31m`, `\x1b[91m`, ...)
+    Ansi16,
+    /// No color codes at all
+    None,
+}
+
+impl ColorDepth {
+    /// Guesses the color depth of the terminal from the `COLORTERM` and `TERM` environment variables.
+    pub fn detect() -> ColorDepth {
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        ColorDepth::from_env(colorterm.as_deref(), term.as_deref())
+    }
+
+    /// Guesses the color depth from the values of `COLORTERM` and `TERM`.
+    ///
+    /// # Arguments
+    /// * `colorterm` - Value of `COLORTERM`, if set
+    /// * `term` - Value of `TERM`, if set
+    ///
+    /// # Returns
+    /// * `ColorDepth` - Truecolor if `COLORTERM` says so, 256 colors for `*256color`
+    ///   terminals, no colors for `dumb` terminals and 16 colors otherwise
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> ColorDepth {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorDepth::TrueColor;
+        }
+        match term {
+            Some(term) if term.contains("truecolor") || term.contains("direct") => {
+                ColorDepth::TrueColor
+            }
+            Some(term) if term.contains("256color") => ColorDepth::Ansi256,
+            Some("dumb") => ColorDepth::None,
+            _ => ColorDepth::Ansi16,
+        }
+    }
+
+    /// The SGR parameters that set a foreground or background color, e.g. `38;2;255;0;0`.
+    ///
+    /// # Arguments
+    /// * `color` - The color to show; it is replaced by the closest palette color if needed
+    /// * `background` - Whether to set the background instead of the foreground
+    ///
+    /// # Returns
+    /// * `Option<String>` - The parameters, or `None` if colors are disabled
+    pub fn sgr(self, color: Rgb<u8>, background: bool) -> Option<String> {
+        match self {
+            ColorDepth::TrueColor => {
+                let Rgb([r, g, b]) = color;
+                let layer = if background { 48 } else { 38 };
+                Some(format!("{layer};2;{r};{g};{b}"))
+            }
+            ColorDepth::Ansi256 => {
+                let layer = if background { 48 } else { 38 };
+                Some(format!("{layer};5;{}", nearest_256(color)))
+            }
+            ColorDepth::Ansi16 => {
+                let index = nearest(color, ansi16_lab()) as u8;
+                // 30-37 and 90-97 for foregrounds, 10 more for backgrounds
+                let base = if index < 8 { 30 } else { 90 - 8 };
+                Some((base + index + if background { 10 } else { 0 }).to_string())
+            }
+            ColorDepth::None => None,
+        }
+    }
+}
+
+impl FromStr for ColorDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            "256" => Ok(ColorDepth::Ansi256),
+            "16" => Ok(ColorDepth::Ansi16),
+            "none" => Ok(ColorDepth::None),
+            _ => Err(format!(
+                "unknown color depth '{s}' (expected truecolor, 256, 16 or none)"
+            )),
+        }
+    }
+}
+
+/// Reads a hex color like `#1e1e1e` (the `#` is optional).
+///
+/// # Arguments
+/// * `s` - Six hexadecimal digits, for red, green and blue
+///
+/// # Returns
+/// * `Ok(Rgb<u8>)` - The color
+/// * `Err(String)` - A message saying why the color was not understood
+pub fn parse_hex(s: &str) -> Result<Rgb<u8>, String> {
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    let channel = |i: usize| {
+        digits
+            .get(i..i + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+    match (digits.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok(Rgb([r, g, b])),
+        _ => Err(format!(
+            "invalid color '{s}' (expected a hex color like #1e1e1e)"
+        )),
+    }
+}
+
+/// The 16 ANSI colors as xterm shows them by default.
+pub const ANSI16: [Rgb<u8>; 16] = [
+    Rgb([0, 0, 0]),
+    Rgb([205, 0, 0]),
+    Rgb([0, 205, 0]),
+    Rgb([205, 205, 0]),
+    Rgb([0, 0, 238]),
+    Rgb([205, 0, 205]),
+    Rgb([0, 205, 205]),
+    Rgb([229, 229, 229]),
+    Rgb([127, 127, 127]),
+    Rgb([255, 0, 0]),
+    Rgb([0, 255, 0]),
+    Rgb([255, 255, 0]),
+    Rgb([92, 92, 255]),
+    Rgb([255, 0, 255]),
+    Rgb([0, 255, 255]),
+    Rgb([255, 255, 255]),
+];
+
+/// Channel levels of the 6x6x6 color cube in the xterm 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Color of an entry of the xterm 256-color palette.
+///
+/// # Arguments
+/// * `index` - Palette index (0-255)
+///
+/// # Returns
+/// * `Rgb<u8>` - The 16 ANSI colors, then the 6x6x6 color cube, then 24 shades of gray
+pub fn xterm_256(index: u8) -> Rgb<u8> {
+    match index {
+        0..=15 => ANSI16[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            Rgb([
+                CUBE_LEVELS[(cube / 36) as usize],
+                CUBE_LEVELS[(cube / 6 % 6) as usize],
+                CUBE_LEVELS[(cube % 6) as usize],
+            ])
+        }
+        232..=255 => {
+            let level = 8 + (index - 232) * 10;
+            Rgb([level; 3])
+        }
+    }
+}
+
+/// Index of the 256-color palette entry that looks closest to `color`.
+///
+/// Only the color cube and the grays are considered, since terminals often
+/// change the first 16 colors with their theme.
+fn nearest_256(color: Rgb<u8>) -> u8 {
+    static PALETTE: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| (16..=255).map(|index| lab(xterm_256(index))).collect());
+    16 + nearest(color, palette) as u8
+}
+
+/// The 16 ANSI colors in CIELAB.
+fn ansi16_lab() -> &'static [[f32; 3]] {
+    static PALETTE: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    PALETTE.get_or_init(|| ANSI16.iter().map(|&color| lab(color)).collect())
+}
+
+/// Position of the palette color (in CIELAB) with the smallest ΔE to `color`.
+fn nearest(color: Rgb<u8>, palette: &[[f32; 3]]) -> usize {
+    let target = lab(color);
+    palette
+        .iter()
+        .map(|candidate| delta_e(target, *candidate))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+/// Converts an sRGB color to CIELAB (D65 white point).
+///
+/// Distances in CIELAB follow how different colors look much better than
+/// distances between RGB values.
+///
+/// # Arguments
+/// * `color` - The sRGB color
+///
+/// # Returns
+/// * `[f32; 3]` - L* (0-100), a* and b*
+pub fn lab(color: Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = color.0.map(|channel| {
+        let channel = channel as f32 / 255.0;
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    });
+
+    // Linear sRGB to XYZ, relative to the D65 white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// The CIE76 color difference (ΔE) between two CIELAB colors.
+///
+/// A difference of about 2.3 is just noticeable.
+pub fn delta_e(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
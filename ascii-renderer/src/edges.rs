@@ -0,0 +1,227 @@
+use image::{DynamicImage, GenericImageView, Rgb, Rgba};
+use std::str::FromStr;
+
+use crate::{color, is_visible};
+
+/// Lookup table for converting binary patterns to ASCII characters.
+/// Each index corresponds to a 4-bit pattern representing a 2x2 grid arrangement.
+/// The comments show the binary pattern each character represents.
+pub const ASCII_LOOKUP: [char; 16] = [
+    ' ', // 0000 - Empty space
+    '~', // 0001 - Bottom-left corner
+    '$', // 0010 - Bottom-right corner
+    '>', // 0011 - Bottom edge
+    '╶', // 0100 - Top-left corner
+    '=', // 0101 - Left edge
+    '<', // 0110 - Diagonal (top-left to bottom-right)
+    '=', // 0111 - Heavy horizontal
+    '^', // 1000 - Top-right corner
+    '+', // 1001 - Diagonal (top-right to bottom-left)
+    '$', // 1010 - Right edge
+    '$', // 1011 - Heavy right side
+    '~', // 1100 - Top edge
+    '*', // 1101 - Heavy intersection
+    '@', // 1110 - Heavy bottom
+    '#', // 1111 - Full block
+];
+
+/// Pattern of a pixel whose whole neighbourhood is similar to it (a flat region).
+pub const FULL: u8 = 0b1111;
+
+/// How the difference between two neighbouring colors is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Distance {
+    /// Squared Euclidean distance of the RGBA values (see [`rgba_distance_squared`])
+    #[default]
+    Rgba,
+    /// Perceptual CIE76 difference (ΔE) in the CIELAB color space
+    Lab,
+}
+
+impl Distance {
+    /// The threshold below which two colors count as similar, if none is given.
+    pub fn default_threshold(self) -> f32 {
+        match self {
+            Distance::Rgba => 50.0,
+            // Around the smallest difference most people can see
+            Distance::Lab => 2.3,
+        }
+    }
+
+    /// Whether two colors are closer than `threshold`.
+    ///
+    /// # Arguments
+    /// * `a` - First RGBA color value
+    /// * `b` - Second RGBA color value
+    /// * `threshold` - Distance from which colors count as different
+    ///
+    /// # Returns
+    /// * `bool` - Whether the colors belong to the same area
+    pub fn similar(self, a: Rgba<u8>, b: Rgba<u8>, threshold: f32) -> bool {
+        match self {
+            Distance::Rgba => (rgba_distance_squared(a, b) as f32) < threshold,
+            Distance::Lab => {
+                // CIELAB has no alpha, so a transparent pixel never matches a visible one
+                let rgb = |Rgba([r, g, b, _]): Rgba<u8>| color::lab(Rgb([r, g, b]));
+                is_visible(a) == is_visible(b) && color::delta_e(rgb(a), rgb(b)) < threshold
+            }
+        }
+    }
+}
+
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgba" | "rgb" => Ok(Distance::Rgba),
+            "lab" => Ok(Distance::Lab),
+            _ => Err(format!("unknown distance '{s}' (expected rgba or lab)")),
+        }
+    }
+}
+
+/// Picks the character for the pixel at `(x, y)` from the shape of its neighbourhood.
+///
+/// # Arguments
+/// * `image` - The image being converted
+/// * `x` - X coordinate of the pixel
+/// * `y` - Y coordinate of the pixel
+/// * `distance` - How color differences are measured
+/// * `threshold` - Distance below which neighbours count as similar
+///
+/// # Returns
+/// * `char` - The character that represents the pixel
+pub fn glyph(image: &DynamicImage, x: u32, y: u32, distance: Distance, threshold: f32) -> char {
+    ASCII_LOOKUP[pattern(image, x, y, distance, threshold) as usize]
+}
+
+/// Computes the marching-squares pattern of the pixel at `(x, y)`.
+///
+/// Neighbours similar in color to the pixel are marked, and the 2x2 corner of
+/// the 3x3 neighbourhood with the most marks is turned into an index for
+/// [`ASCII_LOOKUP`]. Neighbours outside the image are never similar, so
+/// shapes end at the border like at any other edge.
+///
+/// # Arguments
+/// * `image` - The image being converted
+/// * `x` - X coordinate of the pixel
+/// * `y` - Y coordinate of the pixel
+/// * `distance` - How color differences are measured
+/// * `threshold` - Distance below which neighbours count as similar
+///
+/// # Returns
+/// * `u8` - Index value (0-15) representing the pattern
+pub fn pattern(image: &DynamicImage, x: u32, y: u32, distance: Distance, threshold: f32) -> u8 {
+    let rgba = image.get_pixel(x, y);
+
+    // Analyze the neighborhood of the pixel
+    // Creates a 3x3 grid of the pixels around the current pixel, the row above it first
+    let grid: Vec<Vec<u8>> = [-1, 0, 1]
+        .iter()
+        .map(|&dy| {
+            [-1, 0, 1]
+                .iter()
+                .map(|&dx| {
+                    // Convert to binary values based on color similarity
+                    let neighbour = x
+                        .checked_add_signed(dx)
+                        .zip(y.checked_add_signed(dy))
+                        .and_then(|(x, y)| get_pixel_checked(image, x, y));
+                    neighbour.is_some_and(|pixel| distance.similar(pixel, rgba, threshold)) as u8
+                })
+                .collect::<Vec<u8>>()
+        })
+        .collect();
+
+    // Extract all 2x2 grids from the 3x3 grid
+    let subs = extract_2x2(&grid);
+
+    // Find the subgrid with the most active pixels
+    let best = subs
+        .iter()
+        .max_by_key(|&&sub| sub.iter().flatten().sum::<u8>())
+        .unwrap();
+
+    // Convert the pattern to an index
+    marching_square_index(*best)
+}
+
+/// Calculates the squared Euclidean distance between two RGBA colors.
+///
+/// This function computes the sum of squared differences between corresponding
+/// color components of two RGBA values. The result can be used to determine
+/// how similar two colors are - smaller values indicate more similar colors.
+///
+/// # Arguments
+/// * `a` - First RGBA color value
+/// * `b` - Second RGBA color value
+///
+/// # Returns
+/// * `u32` - The squared distance between the colors
+pub fn rgba_distance_squared(a: Rgba<u8>, b: Rgba<u8>) -> u32 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(x, y)| (*x as i32 - *y as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Safely retrieves a pixel from an image at the specified coordinates.
+///
+/// # Arguments
+/// * `image` - Reference to the source image
+/// * `x` - X coordinate of the pixel
+/// * `y` - Y coordinate of the pixel
+///
+/// # Returns
+/// * `Some(Rgba<u8>)` if the coordinates are within the image bounds
+/// * `None` if the coordinates are outside the image bounds
+pub fn get_pixel_checked(image: &DynamicImage, x: u32, y: u32) -> Option<Rgba<u8>> {
+    if x >= image.width() || y >= image.height() {
+        None
+    } else {
+        Some(image.get_pixel(x, y))
+    }
+}
+
+/// Extracts all possible 2x2 subgrids from a 3x3 binary grid.
+///
+/// Takes a 3x3 grid represented as a slice of rows and returns
+/// four 2x2 arrays representing the top-left, top-right, bottom-left,
+/// and bottom-right quadrants of the input grid.
+///
+/// # Arguments
+/// * `grid` - Reference to a 3x3 grid of binary values (0s and 1s)
+///
+/// # Returns
+/// * Vector containing four 2x2 arrays representing each quadrant
+pub fn extract_2x2(grid: &[Vec<u8>]) -> Vec<[[u8; 2]; 2]> {
+    vec![
+        [[grid[0][0], grid[0][1]], [grid[1][0], grid[1][1]]], // top-left
+        [[grid[0][1], grid[0][2]], [grid[1][1], grid[1][2]]], // top-right
+        [[grid[1][0], grid[1][1]], [grid[2][0], grid[2][1]]], // bottom-left
+        [[grid[1][1], grid[1][2]], [grid[2][1], grid[2][2]]], // bottom-right
+    ]
+}
+
+/// Converts a 2x2 binary grid into a marching squares index.
+///
+/// Implements the marching squares algorithm by converting a 2x2 grid
+/// of binary values into a single byte where each bit represents one corner.
+/// The resulting value (0-15) can be used to look up the appropriate
+/// ASCII character for that pattern.
+///
+/// # Arguments
+/// * `square` - 2x2 array of binary values (0s and 1s)
+///
+/// # Returns
+/// * `u8` - Index value (0-15) representing the pattern
+///
+/// # Bit layout
+/// * bit 3: top-left     (square[0][0])
+/// * bit 2: top-right    (square[0][1])
+/// * bit 1: bottom-right (square[1][1])
+/// * bit 0: bottom-left  (square[1][0])
+pub fn marching_square_index(square: [[u8; 2]; 2]) -> u8 {
+    (square[0][0] << 3) | (square[0][1] << 2) | (square[1][1] << 1) | square[1][0]
+}
@@ -0,0 +1,104 @@
+use image::Rgb;
+
+use crate::{Cell, TEXT_COLOR};
+
+/// Foreground and background color of a run of characters.
+type Colors = (Option<Rgb<u8>>, Option<Rgb<u8>>);
+
+/// Writes a color as a CSS hex color, e.g. `#ff8000`.
+pub fn hex(color: Rgb<u8>) -> String {
+    let Rgb([r, g, b]) = color;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Builds a standalone HTML page showing the cells in a `<pre>` block.
+///
+/// Runs of characters with the same colors share one `<span>` with an inline
+/// style, so the page needs no stylesheet besides its own header.
+///
+/// # Arguments
+/// * `cells` - Rows of cells, as produced by [`AsciiRenderer::cells`](crate::AsciiRenderer::cells)
+/// * `colored` - Whether to color the characters; otherwise only the text is written
+/// * `font` - CSS font family of the text
+/// * `background` - Color of the page behind the text
+///
+/// # Returns
+/// * `String` - The HTML page
+pub fn page(cells: &[Vec<Cell>], colored: bool, font: &str, background: Rgb<u8>) -> String {
+    let mut body = String::new();
+
+    for row in cells {
+        // Colors of the open span, if there is one
+        let mut open: Option<Colors> = None;
+
+        for cell in row {
+            if colored {
+                let mut colors = (cell.foreground, cell.background);
+                // The color of a space cannot be seen, so it can join any span
+                if let (' ', Some((foreground, _))) = (cell.ch, open) {
+                    colors.0 = foreground;
+                }
+                if open != Some(colors) {
+                    if open.is_some_and(|open| open != (None, None)) {
+                        body.push_str("</span>");
+                    }
+                    if colors != (None, None) {
+                        body.push_str(&span(colors));
+                    }
+                    open = Some(colors);
+                }
+            }
+            push_escaped(&mut body, cell.ch);
+        }
+
+        if open.is_some_and(|open| open != (None, None)) {
+            body.push_str("</span>");
+        }
+        body.push('\n');
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>ASCII art</title>
+<style>
+body {{ margin: 0; background: {background}; }}
+pre {{ margin: 0; padding: 1em; font-family: {font}, monospace; line-height: 1; color: {text_color}; }}
+</style>
+</head>
+<body>
+<pre>
+{body}</pre>
+</body>
+</html>
+",
+        background = hex(background),
+        text_color = hex(TEXT_COLOR),
+        // Text inside <style> is not unescaped, so characters that could end the rule or tag are dropped
+        font = font.replace(['<', '>', '{', '}'], ""),
+    )
+}
+
+/// The opening tag of a span with the given foreground and background colors.
+fn span((foreground, background): Colors) -> String {
+    let mut style = Vec::new();
+    if let Some(foreground) = foreground {
+        style.push(format!("color:{}", hex(foreground)));
+    }
+    if let Some(background) = background {
+        style.push(format!("background:{}", hex(background)));
+    }
+    format!("<span style=\"{}\">", style.join(";"))
+}
+
+/// Appends a character, escaped if HTML would read it as markup.
+fn push_escaped(buff: &mut String, ch: char) {
+    match ch {
+        '&' => buff.push_str("&amp;"),
+        '<' => buff.push_str("&lt;"),
+        '>' => buff.push_str("&gt;"),
+        _ => buff.push(ch),
+    }
+}
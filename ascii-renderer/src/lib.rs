@@ -0,0 +1,615 @@
+//! Converts images into (optionally colored) ASCII art.
+//!
+//! This is the renderer shared by `image-to-ascii` and `ascii-animations`:
+//! configure an [`AsciiRenderer`] with its builder, [`resize`](AsciiRenderer::resize)
+//! an image to the output size and [`render`](AsciiRenderer::render) it.
+
+pub mod blocks;
+pub mod braille;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod color;
+pub mod edges;
+pub mod html;
+pub mod png;
+pub mod ramp;
+pub mod svg;
+mod typeset;
+
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba};
+use std::str::FromStr;
+
+pub use color::ColorDepth;
+pub use edges::Distance;
+
+/// Default scaling factor for character width compensation.
+/// Characters are typically taller than wide, so the image is stretched horizontally.
+pub const DEFAULT_FATNESS: f32 = 2.45;
+
+/// Default height of a character cell relative to its width, typical for terminal fonts.
+pub const DEFAULT_CELL_ASPECT: f32 = 2.0;
+
+/// Pixels with less opacity than this are rendered as empty space.
+const MIN_ALPHA: f32 = 0.25;
+
+/// Color of uncolored characters in HTML and images, like the default text color of a terminal.
+const TEXT_COLOR: Rgb<u8> = Rgb([0xcc, 0xcc, 0xcc]);
+
+/// Whether a pixel is opaque enough to be drawn.
+fn is_visible(rgba: Rgba<u8>) -> bool {
+    rgba.0[3] as f32 / 255f32 >= MIN_ALPHA
+}
+
+/// How the character for each pixel is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Marching-squares shapes from the similarity of neighbouring pixels
+    #[default]
+    Edges,
+    /// Characters from a ramp, by perceived luminance
+    Ramp,
+    /// Edge shapes for contours, the luminance ramp for flat regions
+    Hybrid,
+    /// Braille patterns, each showing a 2x4 block of pixels as dots
+    Braille,
+    /// Upper half blocks with separate foreground and background colors: two pixels per character
+    Halfblock,
+    /// Quadrant blocks in the two colors that best fit a 2x2 block of pixels
+    Quadrant,
+}
+
+impl Mode {
+    /// How many pixels (horizontally, vertically) one character stands for.
+    pub fn cell_size(self) -> (u32, u32) {
+        match self {
+            Mode::Edges | Mode::Ramp | Mode::Hybrid => (1, 1),
+            Mode::Braille => braille::CELL_SIZE,
+            Mode::Halfblock => blocks::HALFBLOCK_SIZE,
+            Mode::Quadrant => blocks::QUADRANT_SIZE,
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "edges" => Ok(Mode::Edges),
+            "ramp" => Ok(Mode::Ramp),
+            "hybrid" => Ok(Mode::Hybrid),
+            "braille" => Ok(Mode::Braille),
+            "halfblock" => Ok(Mode::Halfblock),
+            "quadrant" => Ok(Mode::Quadrant),
+            _ => Err(format!(
+                "unknown mode '{s}' (expected edges, ramp, hybrid, braille, halfblock or quadrant)"
+            )),
+        }
+    }
+}
+
+/// How the size of the output is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fit {
+    /// Exactly the width (times the fatness) and height, whatever the shape of the image
+    #[default]
+    Stretch,
+    /// Exactly the width, with as many rows as keep the image's aspect ratio
+    Width,
+    /// As large as fits into the width and height while keeping the image's aspect ratio
+    Within,
+}
+
+/// Columns and rows of the terminal, for fitting the output into it.
+///
+/// # Returns
+/// * `Option<(u32, u32)>` - The size of the terminal stdout is written to or,
+///   if that is redirected, the one of stderr; `None` outside a terminal
+pub fn terminal_size() -> Option<(u32, u32)> {
+    let (terminal_size::Width(columns), terminal_size::Height(rows)) =
+        terminal_size::terminal_size()
+            .or_else(|| terminal_size::terminal_size_of(std::io::stderr()))?;
+    Some((columns as u32, rows as u32))
+}
+
+/// What kind of file the art is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Text with ANSI color escape codes, for terminals
+    #[default]
+    Ansi,
+    /// A standalone HTML page, for browsers
+    Html,
+    /// A vector image typeset in the bundled font
+    Svg,
+    /// A raster image typeset in the bundled font
+    Png,
+}
+
+impl Format {
+    /// The usual file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Ansi => "txt",
+            Format::Html => "html",
+            Format::Svg => "svg",
+            Format::Png => "png",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(Format::Ansi),
+            "html" => Ok(Format::Html),
+            "svg" => Ok(Format::Svg),
+            "png" => Ok(Format::Png),
+            _ => Err(format!(
+                "unknown format '{s}' (expected ansi, html, svg or png)"
+            )),
+        }
+    }
+}
+
+/// One character of output and its colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    /// Color of the character; `None` leaves it uncolored, like transparent pixels
+    pub foreground: Option<Rgb<u8>>,
+    /// Color behind the character; `None` keeps the terminal's background
+    pub background: Option<Rgb<u8>>,
+}
+
+impl Cell {
+    /// An empty, uncolored cell.
+    pub const BLANK: Cell = Cell {
+        ch: ' ',
+        foreground: None,
+        background: None,
+    };
+}
+
+/// Renders images as ASCII art.
+///
+/// Create one with [`AsciiRenderer::builder`]; the defaults match the
+/// command-line tools (20x20 characters, colored output).
+#[derive(Debug, Clone)]
+pub struct AsciiRenderer {
+    width: u32,
+    height: u32,
+    fatness: f32,
+    fit: Fit,
+    /// Height of a character cell relative to its width
+    cell_aspect: f32,
+    gray_scale: bool,
+    /// Which escape codes colors are written with
+    color_depth: ColorDepth,
+    /// How much (per channel) a color may differ from the previous one and still be drawn with it
+    color_tolerance: u8,
+    mode: Mode,
+    /// How the edge shapes measure the difference between neighbouring colors
+    distance: Distance,
+    /// Distance below which neighbours are part of the same shape, `None` for the distance's default
+    threshold: Option<f32>,
+    /// CSS font family of HTML output
+    font: String,
+    /// Page color behind HTML and image output
+    background: Rgb<u8>,
+    /// Size of the text in SVG and PNG output, in pixels to the em
+    font_size: f32,
+    /// Characters from darkest to brightest, never empty
+    ramp: Vec<char>,
+    /// Luminance from which a braille dot is lit
+    brightness_threshold: f32,
+    /// Spread the rounding error of braille dots with Floyd-Steinberg dithering
+    dither: bool,
+}
+
+/// Builder for [`AsciiRenderer`].
+#[derive(Debug, Clone)]
+pub struct AsciiRendererBuilder {
+    renderer: AsciiRenderer,
+}
+
+impl Default for AsciiRenderer {
+    fn default() -> Self {
+        AsciiRenderer {
+            width: 20,
+            height: 20,
+            fatness: DEFAULT_FATNESS,
+            fit: Fit::Stretch,
+            cell_aspect: DEFAULT_CELL_ASPECT,
+            gray_scale: false,
+            color_depth: ColorDepth::TrueColor,
+            color_tolerance: 0,
+            mode: Mode::Edges,
+            distance: Distance::Rgba,
+            threshold: None,
+            font: "monospace".to_string(),
+            background: Rgb([0, 0, 0]),
+            font_size: 16.0,
+            ramp: ramp::DEFAULT_RAMP.chars().collect(),
+            brightness_threshold: 0.5,
+            dither: false,
+        }
+    }
+}
+
+impl AsciiRenderer {
+    /// Starts building a renderer from the default settings.
+    pub fn builder() -> AsciiRendererBuilder {
+        AsciiRendererBuilder {
+            renderer: AsciiRenderer::default(),
+        }
+    }
+
+    /// Resizes an image to the output size (see [`size`](Self::size)).
+    ///
+    /// # Arguments
+    /// * `image` - The source image
+    ///
+    /// # Returns
+    /// * `DynamicImage` - An image with as many pixels per output character as the mode needs
+    pub fn resize(&self, image: &DynamicImage) -> DynamicImage {
+        let (columns, rows) = self.size(image.width(), image.height());
+        let (cell_width, cell_height) = self.mode.cell_size();
+        image.resize_exact(
+            columns * cell_width,
+            rows * cell_height,
+            image::imageops::FilterType::Nearest,
+        )
+    }
+
+    /// Computes the size of the output for an image, according to the fit.
+    ///
+    /// To keep the aspect ratio, the image's height is divided by the cell
+    /// aspect: a character twice as tall as it is wide covers two rows' worth
+    /// of pixels per column.
+    ///
+    /// # Arguments
+    /// * `image_width` - Width of the source image in pixels
+    /// * `image_height` - Height of the source image in pixels
+    ///
+    /// # Returns
+    /// * `(u32, u32)` - Columns and rows of characters, each at least 1
+    pub fn size(&self, image_width: u32, image_height: u32) -> (u32, u32) {
+        // Rows of output per column that show the image undistorted
+        let aspect = image_height as f32 / image_width.max(1) as f32 / self.cell_aspect;
+        let rows_for = |columns: u32| ((columns as f32 * aspect).round() as u32).max(1);
+
+        match self.fit {
+            Fit::Stretch => ((self.width as f32 * self.fatness) as u32, self.height),
+            Fit::Width => (self.width, rows_for(self.width)),
+            Fit::Within => {
+                let rows = rows_for(self.width);
+                if rows <= self.height {
+                    return (self.width, rows);
+                }
+                // Too tall at full width, so the height limits the width instead
+                let columns = (self.height as f32 / aspect).round() as u32;
+                (columns.clamp(1, self.width.max(1)), self.height)
+            }
+        }
+    }
+
+    /// Converts an image that is already at output size (see [`resize`](Self::resize)) to ASCII art.
+    ///
+    /// Characters are colored with ANSI escape codes for the color depth,
+    /// unless the renderer is in gray scale mode. A color is only written
+    /// where it changes, and the colors are reset at the end of every row,
+    /// which ends with a newline.
+    ///
+    /// # Arguments
+    /// * `image` - The resized image
+    ///
+    /// # Returns
+    /// * `String` - The ASCII art
+    pub fn render(&self, image: &DynamicImage) -> String {
+        let plain = self.gray_scale || self.color_depth == ColorDepth::None;
+        let mut buff = String::new();
+
+        // Combine all characters into a single string
+        for row in self.cells(image) {
+            // Colors currently set in the terminal, None being its default
+            let mut foreground = None;
+            let mut background = None;
+
+            for cell in row {
+                if plain {
+                    buff.push(cell.ch);
+                    continue;
+                }
+
+                let mut codes = Vec::new();
+                // The foreground of a space is invisible, so it can stay as it is
+                if cell.ch != ' ' && !self.same_color(foreground, cell.foreground, false) {
+                    foreground = cell.foreground;
+                    codes.push(self.sgr(foreground, false));
+                }
+                if !self.same_color(background, cell.background, true) {
+                    background = cell.background;
+                    codes.push(self.sgr(background, true));
+                }
+                if !codes.is_empty() {
+                    buff.push_str(&format!("\x1b[{}m", codes.join(";")));
+                }
+                buff.push(cell.ch);
+            }
+
+            if foreground.is_some() || background.is_some() {
+                buff.push_str("\x1b[0m");
+            }
+            // Add new line between rows
+            buff.push('\n');
+        }
+        buff
+    }
+
+    /// Converts an image that is already at output size to a standalone HTML page.
+    ///
+    /// The art is shown in a `<pre>` block in the renderer's font and on its
+    /// background. Colors are written in full, whatever the color depth,
+    /// unless the renderer is in gray scale mode.
+    ///
+    /// # Arguments
+    /// * `image` - The resized image
+    ///
+    /// # Returns
+    /// * `String` - The HTML page
+    pub fn render_html(&self, image: &DynamicImage) -> String {
+        html::page(
+            &self.cells(image),
+            !self.gray_scale,
+            &self.font,
+            self.background,
+        )
+    }
+
+    /// Converts an image that is already at output size to an SVG image.
+    ///
+    /// The characters are drawn as paths from the bundled font at the
+    /// renderer's font size, on its background, so the image looks the same
+    /// everywhere.
+    ///
+    /// # Arguments
+    /// * `image` - The resized image
+    ///
+    /// # Returns
+    /// * `String` - The SVG document
+    pub fn render_svg(&self, image: &DynamicImage) -> String {
+        svg::document(
+            &self.cells(image),
+            !self.gray_scale,
+            self.background,
+            self.font_size,
+        )
+    }
+
+    /// Converts an image that is already at output size to a picture of the text.
+    ///
+    /// Like [`render_svg`](Self::render_svg), but rasterized, e.g. to be saved as a PNG.
+    ///
+    /// # Arguments
+    /// * `image` - The resized image
+    ///
+    /// # Returns
+    /// * `RgbImage` - The picture
+    pub fn render_png(&self, image: &DynamicImage) -> RgbImage {
+        png::image(
+            &self.cells(image),
+            !self.gray_scale,
+            self.background,
+            self.font_size,
+        )
+    }
+
+    /// Whether a cell colored `wanted` can keep the color `current` the terminal is set to.
+    ///
+    /// That is the case if both are written with the same escape code at the
+    /// color depth, or if they differ by no more than the color tolerance.
+    fn same_color(
+        &self,
+        current: Option<Rgb<u8>>,
+        wanted: Option<Rgb<u8>>,
+        background: bool,
+    ) -> bool {
+        match (current, wanted) {
+            (None, None) => true,
+            (Some(current), Some(wanted)) => {
+                let close = current
+                    .0
+                    .iter()
+                    .zip(wanted.0.iter())
+                    .all(|(a, b)| a.abs_diff(*b) <= self.color_tolerance);
+                close
+                    || self.color_depth.sgr(current, background)
+                        == self.color_depth.sgr(wanted, background)
+            }
+            _ => false,
+        }
+    }
+
+    /// The SGR parameters that switch to `color`, or back to the terminal's default color.
+    fn sgr(&self, color: Option<Rgb<u8>>, background: bool) -> String {
+        match color {
+            Some(color) => self.color_depth.sgr(color, background).unwrap_or_default(),
+            None if background => "49".to_string(),
+            None => "39".to_string(),
+        }
+    }
+
+    /// Converts an image that is already at output size into rows of characters and their colors.
+    ///
+    /// # Arguments
+    /// * `image` - The resized image
+    ///
+    /// # Returns
+    /// * `Vec<Vec<Cell>>` - One row of cells per line of output
+    pub fn cells(&self, image: &DynamicImage) -> Vec<Vec<Cell>> {
+        match self.mode {
+            Mode::Braille => return braille::cells(image, self.brightness_threshold, self.dither),
+            Mode::Halfblock => return blocks::halfblock_cells(image),
+            Mode::Quadrant => return blocks::quadrant_cells(image),
+            Mode::Edges | Mode::Ramp | Mode::Hybrid => {}
+        }
+
+        // 2d vector to store the output
+        let mut output_raster = vec![vec![Cell::BLANK; image.width() as usize]; image.height() as usize];
+
+        for (x, y, rgba) in image.pixels() {
+            // Skip Transparent pixels. They stay blank in the output
+            if !is_visible(rgba) {
+                continue;
+            }
+
+            let [r, g, b, _] = rgba.0;
+            output_raster[y as usize][x as usize] = Cell {
+                ch: self.glyph(image, x, y, rgba),
+                foreground: Some(Rgb([r, g, b])),
+                background: None,
+            };
+        }
+        output_raster
+    }
+
+    /// Picks the character for one pixel according to the mode.
+    fn glyph(&self, image: &DynamicImage, x: u32, y: u32, rgba: Rgba<u8>) -> char {
+        let threshold = self
+            .threshold
+            .unwrap_or_else(|| self.distance.default_threshold());
+        match self.mode {
+            Mode::Edges => edges::glyph(image, x, y, self.distance, threshold),
+            Mode::Ramp => ramp::ramp_char(&self.ramp, ramp::luminance(rgba)),
+            Mode::Hybrid => match edges::pattern(image, x, y, self.distance, threshold) {
+                edges::FULL => ramp::ramp_char(&self.ramp, ramp::luminance(rgba)),
+                pattern => edges::ASCII_LOOKUP[pattern as usize],
+            },
+            Mode::Braille | Mode::Halfblock | Mode::Quadrant => {
+                unreachable!("block modes are rendered a block at a time")
+            }
+        }
+    }
+}
+
+impl AsciiRendererBuilder {
+    /// Width of the output in characters. When stretching, the fatness factor is applied to it.
+    pub fn width(mut self, width: u32) -> Self {
+        self.renderer.width = width;
+        self
+    }
+
+    /// Height of the output in characters. With [`Fit::Within`] it is the most
+    /// rows that are used, with [`Fit::Width`] it is not used at all.
+    pub fn height(mut self, height: u32) -> Self {
+        self.renderer.height = height;
+        self
+    }
+
+    /// Scaling factor for character width compensation (see [`DEFAULT_FATNESS`]).
+    /// Only used by [`Fit::Stretch`].
+    pub fn fatness(mut self, fatness: f32) -> Self {
+        self.renderer.fatness = fatness;
+        self
+    }
+
+    /// How the width and height are turned into the output size (see [`Fit`]).
+    pub fn fit(mut self, fit: Fit) -> Self {
+        self.renderer.fit = fit;
+        self
+    }
+
+    /// Height of a character cell relative to its width (see [`DEFAULT_CELL_ASPECT`]),
+    /// used to keep the aspect ratio when fitting.
+    pub fn cell_aspect(mut self, cell_aspect: f32) -> Self {
+        self.renderer.cell_aspect = cell_aspect;
+        self
+    }
+
+    /// Outputs plain characters without color codes.
+    pub fn gray_scale(mut self, gray_scale: bool) -> Self {
+        self.renderer.gray_scale = gray_scale;
+        self
+    }
+
+    /// Which escape codes colors are written with (see [`ColorDepth`]).
+    /// Colors the depth cannot show are replaced by the closest one it can.
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.renderer.color_depth = color_depth;
+        self
+    }
+
+    /// How much each channel of a color may differ from the color before it
+    /// and still be drawn with that one. Larger tolerances save escape codes
+    /// at the cost of color accuracy; 0 keeps every color exact.
+    pub fn color_tolerance(mut self, tolerance: u8) -> Self {
+        self.renderer.color_tolerance = tolerance;
+        self
+    }
+
+    /// How characters are chosen (see [`Mode`]).
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.renderer.mode = mode;
+        self
+    }
+
+    /// CSS font family for HTML output, e.g. `"Fira Code"`. A monospace font is
+    /// used as the fallback.
+    pub fn font(mut self, font: &str) -> Self {
+        self.renderer.font = font.to_string();
+        self
+    }
+
+    /// Color behind HTML, SVG and PNG output.
+    pub fn background(mut self, background: Rgb<u8>) -> Self {
+        self.renderer.background = background;
+        self
+    }
+
+    /// Size of the text in SVG and PNG output, in pixels to the em.
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.renderer.font_size = font_size;
+        self
+    }
+
+    /// How the edge shapes measure the difference between neighbouring colors (see [`Distance`]).
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.renderer.distance = distance;
+        self
+    }
+
+    /// Distance below which neighbouring colors count as the same area in the
+    /// edges and hybrid modes. Defaults to [`Distance::default_threshold`].
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.renderer.threshold = Some(threshold);
+        self
+    }
+
+    /// Characters used by the ramp and hybrid modes, from darkest to brightest.
+    /// An empty ramp keeps the current one.
+    pub fn ramp(mut self, ramp: &str) -> Self {
+        if !ramp.is_empty() {
+            self.renderer.ramp = ramp.chars().collect();
+        }
+        self
+    }
+
+    /// Luminance (0.0-1.0) from which a braille dot is lit.
+    pub fn brightness_threshold(mut self, threshold: f32) -> Self {
+        self.renderer.brightness_threshold = threshold;
+        self
+    }
+
+    /// Uses Floyd-Steinberg dithering for braille dots instead of a hard threshold.
+    pub fn dither(mut self, dither: bool) -> Self {
+        self.renderer.dither = dither;
+        self
+    }
+
+    pub fn build(self) -> AsciiRenderer {
+        self.renderer
+    }
+}
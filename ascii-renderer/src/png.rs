@@ -0,0 +1,135 @@
+use ab_glyph::{Font, FontRef, point};
+use image::{Rgb, RgbImage};
+
+use crate::typeset::{self, Glyph, Grid, Shape};
+use crate::{Cell, TEXT_COLOR};
+
+/// Draws the cells into an image, typeset in the bundled font.
+///
+/// # Arguments
+/// * `cells` - Rows of cells, as produced by [`AsciiRenderer::cells`](crate::AsciiRenderer::cells)
+/// * `colored` - Whether to color the characters; otherwise they are drawn in the text color
+/// * `background` - Color of the image behind the text
+/// * `font_size` - Size of the text in pixels to the em
+///
+/// # Returns
+/// * `RgbImage` - The image, ready to be saved as a PNG
+pub fn image(cells: &[Vec<Cell>], colored: bool, background: Rgb<u8>, font_size: f32) -> RgbImage {
+    let font = typeset::font();
+    let grid = Grid::new(&font, font_size);
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let mut canvas = RgbImage::from_pixel(
+        columns * grid.cell_width,
+        cells.len() as u32 * grid.cell_height,
+        background,
+    );
+
+    for (row, line) in cells.iter().enumerate() {
+        for (column, cell) in line.iter().enumerate() {
+            let (x, y) = (
+                column as u32 * grid.cell_width,
+                row as u32 * grid.cell_height,
+            );
+
+            if let Some(color) = cell.background.filter(|_| colored) {
+                for dy in 0..grid.cell_height {
+                    for dx in 0..grid.cell_width {
+                        canvas.put_pixel(x + dx, y + dy, color);
+                    }
+                }
+            }
+
+            let color = match cell.foreground {
+                Some(color) if colored => color,
+                _ => TEXT_COLOR,
+            };
+            draw(&mut canvas, &font, grid, cell.ch, (x, y), color);
+        }
+    }
+    canvas
+}
+
+/// Draws one character into the cell whose top-left corner is at `origin`.
+fn draw(
+    canvas: &mut RgbImage,
+    font: &FontRef,
+    grid: Grid,
+    ch: char,
+    origin: (u32, u32),
+    color: Rgb<u8>,
+) {
+    let (x, y) = origin;
+    // Lets a character cover part of a pixel, blending it with what is already there
+    let mut blend = |px: i64, py: i64, coverage: f32| {
+        if px < 0 || py < 0 || coverage <= 0.0 {
+            return;
+        }
+        let Some(pixel) = canvas.get_pixel_mut_checked(px as u32, py as u32) else {
+            return;
+        };
+        let coverage = coverage.min(1.0);
+        for (channel, target) in pixel.0.iter_mut().zip(color.0) {
+            *channel =
+                (*channel as f32 * (1.0 - coverage) + target as f32 * coverage).round() as u8;
+        }
+    };
+
+    match typeset::glyph(font, grid, ch) {
+        Glyph::Empty => {}
+        Glyph::Shapes(shapes) => {
+            for shape in shapes {
+                match shape {
+                    Shape::Rect {
+                        x: left,
+                        y: top,
+                        width,
+                        height,
+                    } => {
+                        for dy in top as i64..(top + height) as i64 {
+                            for dx in left as i64..(left + width) as i64 {
+                                blend(x as i64 + dx, y as i64 + dy, 1.0);
+                            }
+                        }
+                    }
+                    Shape::Dot {
+                        x: center_x,
+                        y: center_y,
+                        radius,
+                    } => {
+                        let (top, bottom) = (
+                            (center_y - radius).floor() as i64,
+                            (center_y + radius).ceil() as i64,
+                        );
+                        let (left, right) = (
+                            (center_x - radius).floor() as i64,
+                            (center_x + radius).ceil() as i64,
+                        );
+                        for dy in top..bottom {
+                            for dx in left..right {
+                                // Distance from the dot's edge to the pixel's center gives a smooth border
+                                let distance = ((dx as f32 + 0.5 - center_x).powi(2)
+                                    + (dy as f32 + 0.5 - center_y).powi(2))
+                                .sqrt();
+                                blend(x as i64 + dx, y as i64 + dy, radius + 0.5 - distance);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Glyph::Font(id) => {
+            let glyph =
+                id.with_scale_and_position(grid.scale, point(x as f32, y as f32 + grid.baseline));
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|dx, dy, coverage| {
+                    blend(
+                        bounds.min.x as i64 + dx as i64,
+                        bounds.min.y as i64 + dy as i64,
+                        coverage,
+                    )
+                });
+            }
+        }
+    }
+}
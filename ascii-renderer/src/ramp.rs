@@ -0,0 +1,32 @@
+use image::Rgba;
+
+/// Default characters for luminance ramps, from darkest to brightest.
+pub const DEFAULT_RAMP: &str = " .:-=+*#%@";
+
+/// Calculates the perceived luminance of a color.
+///
+/// Uses the Rec. 709 weights: green contributes most to perceived brightness
+/// and blue the least.
+///
+/// # Arguments
+/// * `rgba` - The color (alpha is ignored)
+///
+/// # Returns
+/// * `f32` - Luminance between 0.0 (black) and 1.0 (white)
+pub fn luminance(rgba: Rgba<u8>) -> f32 {
+    let [r, g, b, _] = rgba.0;
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0
+}
+
+/// Maps a luminance onto a character ramp.
+///
+/// # Arguments
+/// * `ramp` - Characters from darkest to brightest (must not be empty)
+/// * `luminance` - Luminance between 0.0 and 1.0
+///
+/// # Returns
+/// * `char` - The ramp character for that brightness
+pub fn ramp_char(ramp: &[char], luminance: f32) -> char {
+    let index = (luminance.clamp(0.0, 1.0) * (ramp.len() - 1) as f32).round() as usize;
+    ramp[index]
+}
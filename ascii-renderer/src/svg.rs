@@ -0,0 +1,162 @@
+use ab_glyph::{Font, FontRef, OutlineCurve, Point};
+use image::Rgb;
+use std::collections::HashMap;
+
+use crate::typeset::{self, Glyph, Grid, Shape};
+use crate::{Cell, TEXT_COLOR, html::hex};
+
+/// Builds an SVG image of the cells, typeset in the bundled font.
+///
+/// Every character that is drawn becomes a path, defined once and placed
+/// with `<use>` wherever it appears, so the image does not depend on the
+/// fonts of the machine that shows it.
+///
+/// # Arguments
+/// * `cells` - Rows of cells, as produced by [`AsciiRenderer::cells`](crate::AsciiRenderer::cells)
+/// * `colored` - Whether to color the characters; otherwise they are drawn in the text color
+/// * `background` - Color of the image behind the text
+/// * `font_size` - Size of the text in pixels to the em
+///
+/// # Returns
+/// * `String` - The SVG document
+pub fn document(cells: &[Vec<Cell>], colored: bool, background: Rgb<u8>, font_size: f32) -> String {
+    let font = typeset::font();
+    let grid = Grid::new(&font, font_size);
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let (width, height) = (
+        columns * grid.cell_width,
+        cells.len() as u32 * grid.cell_height,
+    );
+
+    // Definitions of the characters in order of first use, so the output is reproducible
+    let mut defs = String::new();
+    let mut ids: HashMap<char, Option<usize>> = HashMap::new();
+    let mut next_id = 0;
+    let mut backgrounds = String::new();
+    let mut text = String::new();
+
+    for (row, line) in cells.iter().enumerate() {
+        let y = row as u32 * grid.cell_height;
+
+        // Cell backgrounds, with runs of the same color merged into one rectangle
+        let mut column = 0;
+        while column < line.len() {
+            let color = line[column].background.filter(|_| colored);
+            let run = line[column..]
+                .iter()
+                .take_while(|cell| cell.background.filter(|_| colored) == color)
+                .count();
+            if let Some(color) = color {
+                backgrounds.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{y}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    column as u32 * grid.cell_width,
+                    run as u32 * grid.cell_width,
+                    grid.cell_height,
+                    hex(color)
+                ));
+            }
+            column += run;
+        }
+
+        for (column, cell) in line.iter().enumerate() {
+            let id = *ids.entry(cell.ch).or_insert_with(|| {
+                let shape = definition(&font, grid, cell.ch)?;
+                let id = next_id;
+                next_id += 1;
+                defs.push_str(&format!("<g id=\"c{id}\">{shape}</g>\n"));
+                Some(id)
+            });
+            let Some(id) = id else {
+                continue;
+            };
+            let color = match cell.foreground {
+                Some(color) if colored => color,
+                _ => TEXT_COLOR,
+            };
+            text.push_str(&format!(
+                "<use href=\"#c{id}\" x=\"{}\" y=\"{y}\" fill=\"{}\"/>\n",
+                column as u32 * grid.cell_width,
+                hex(color)
+            ));
+        }
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">
+<defs>
+{defs}</defs>
+<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>
+<g shape-rendering=\"crispEdges\">
+{backgrounds}</g>
+{text}</svg>
+",
+        hex(background)
+    )
+}
+
+/// The SVG elements that draw a character in a cell whose top-left corner is at the origin.
+///
+/// # Returns
+/// * `Option<String>` - The elements, or `None` if nothing is drawn for the character
+fn definition(font: &FontRef, grid: Grid, ch: char) -> Option<String> {
+    match typeset::glyph(font, grid, ch) {
+        Glyph::Empty => None,
+        Glyph::Shapes(shapes) => Some(
+            shapes
+                .iter()
+                .map(|shape| match *shape {
+                    Shape::Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    } => format!(
+                        "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" shape-rendering=\"crispEdges\"/>"
+                    ),
+                    Shape::Dot { x, y, radius } => {
+                        format!("<circle cx=\"{x}\" cy=\"{y}\" r=\"{radius:.2}\"/>")
+                    }
+                })
+                .collect(),
+        ),
+        Glyph::Font(id) => {
+            let outline = font.outline(id)?;
+            // Font units point up from the baseline, SVG coordinates point down from the top
+            let point = |p: Point| {
+                format!(
+                    "{:.2} {:.2}",
+                    p.x * grid.units,
+                    grid.baseline - p.y * grid.units
+                )
+            };
+
+            let mut path = String::new();
+            let mut end = None;
+            for curve in &outline.curves {
+                let (start, last) = match *curve {
+                    OutlineCurve::Line(start, to) => (start, to),
+                    OutlineCurve::Quad(start, _, to) => (start, to),
+                    OutlineCurve::Cubic(start, _, _, to) => (start, to),
+                };
+                // A curve that does not continue the previous one starts a new contour
+                if end != Some(start) {
+                    path.push_str(&format!("M{}", point(start)));
+                }
+                match *curve {
+                    OutlineCurve::Line(_, to) => path.push_str(&format!("L{}", point(to))),
+                    OutlineCurve::Quad(_, control, to) => {
+                        path.push_str(&format!("Q{} {}", point(control), point(to)))
+                    }
+                    OutlineCurve::Cubic(_, first, second, to) => path.push_str(&format!(
+                        "C{} {} {}",
+                        point(first),
+                        point(second),
+                        point(to)
+                    )),
+                }
+                end = Some(last);
+            }
+            Some(format!("<path d=\"{path}\"/>"))
+        }
+    }
+}
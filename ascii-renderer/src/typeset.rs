@@ -0,0 +1,124 @@
+use ab_glyph::{Font, FontRef, GlyphId, PxScale};
+
+use crate::{blocks, braille};
+
+/// DejaVu Sans Mono, bundled so images look the same on every machine (see `fonts/LICENSE`).
+const FONT: &[u8] = include_bytes!("../fonts/DejaVuSansMono.ttf");
+
+/// Radius of a braille dot, relative to the width of a cell.
+const DOT_RADIUS: f32 = 0.18;
+
+/// The bundled font.
+pub(crate) fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(FONT).expect("the bundled font is a valid TrueType font")
+}
+
+/// Size of a character cell and where text sits in it, for one font size.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Grid {
+    /// Width of a cell in pixels
+    pub cell_width: u32,
+    /// Height of a cell in pixels (one line, without any gap between lines)
+    pub cell_height: u32,
+    /// Distance from the top of a cell to the baseline of its text
+    pub baseline: f32,
+    /// Scale that makes the font `font_size` pixels to the em
+    pub scale: PxScale,
+    /// Pixels per font unit
+    pub units: f32,
+}
+
+impl Grid {
+    /// Lays out cells for text `font_size` pixels to the em.
+    pub fn new(font: &FontRef, font_size: f32) -> Grid {
+        let units = font_size / font.units_per_em().unwrap_or(1000.0);
+        let advance = font.h_advance_unscaled(font.glyph_id('M'));
+        Grid {
+            cell_width: ((advance * units).round() as u32).max(1),
+            cell_height: ((font.height_unscaled() * units).round() as u32).max(1),
+            baseline: font.ascent_unscaled() * units,
+            scale: PxScale::from(font.height_unscaled() * units),
+            units,
+        }
+    }
+}
+
+/// A filled shape, in pixels from the top-left corner of its cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    Dot {
+        x: f32,
+        y: f32,
+        radius: f32,
+    },
+}
+
+/// How a character is drawn.
+pub(crate) enum Glyph {
+    /// Nothing to draw, like a space
+    Empty,
+    /// Shapes that exactly fill their part of the cell, so blocks and braille line up without gaps
+    Shapes(Vec<Shape>),
+    /// A glyph of the bundled font
+    Font(GlyphId),
+}
+
+/// Decides how to draw a character.
+///
+/// # Arguments
+/// * `font` - The bundled font
+/// * `grid` - The size of the cells
+/// * `ch` - The character
+///
+/// # Returns
+/// * `Glyph` - Block and braille characters as shapes, anything else from the font
+pub(crate) fn glyph(font: &FontRef, grid: Grid, ch: char) -> Glyph {
+    if ch.is_whitespace() {
+        return Glyph::Empty;
+    }
+
+    let (width, height) = (grid.cell_width, grid.cell_height);
+    if let Some(mask) = blocks::quadrants(ch) {
+        // Split at whole pixels so neighbouring quadrants do not overlap
+        let columns = [(0, width / 2), (width / 2, width - width / 2)];
+        let rows = [(0, height / 2), (height / 2, height - height / 2)];
+        let shapes = (0..4)
+            .filter(|quadrant| mask & (1 << quadrant) != 0)
+            .map(|quadrant| {
+                let (x, width) = columns[quadrant % 2];
+                let (y, height) = rows[quadrant / 2];
+                Shape::Rect {
+                    x: x as f32,
+                    y: y as f32,
+                    width: width as f32,
+                    height: height as f32,
+                }
+            })
+            .collect();
+        return Glyph::Shapes(shapes);
+    }
+
+    if let Some(dots) = braille::raised(ch) {
+        let (dot_width, dot_height) = (width as f32 / 2.0, height as f32 / 4.0);
+        let shapes = dots
+            .into_iter()
+            .map(|(column, row)| Shape::Dot {
+                x: (column as f32 + 0.5) * dot_width,
+                y: (row as f32 + 0.5) * dot_height,
+                radius: DOT_RADIUS * width as f32,
+            })
+            .collect::<Vec<_>>();
+        return match shapes.is_empty() {
+            true => Glyph::Empty,
+            false => Glyph::Shapes(shapes),
+        };
+    }
+
+    Glyph::Font(font.glyph_id(ch))
+}
@@ -0,0 +1,179 @@
+//! Colors are only written where they change, which must not change what the terminal shows.
+
+use ascii_renderer::{AsciiRenderer, Cell, Mode};
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+
+/// A picture with flat areas, a gradient, transparent pixels and edges.
+fn picture() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(120, 60, |x, y| match (x / 40, y / 20) {
+        (0, _) => Rgba([200, 30, 30, 255]),
+        (1, 0) => Rgba([0, 0, 0, 0]),
+        (1, _) => Rgba([(x * 2) as u8, (y * 4) as u8, 90, 255]),
+        _ if (x + y) % 7 == 0 => Rgba([250, 250, 250, 255]),
+        _ => Rgba([20, 60, 200, 255]),
+    }))
+}
+
+/// What a terminal shows for one character: the character and its colors.
+type Shown = (char, Option<Rgb<u8>>, Option<Rgb<u8>>);
+
+/// Plays the output like a 24-bit color terminal would.
+fn interpret(output: &str) -> Vec<Vec<Shown>> {
+    output
+        .lines()
+        .map(|line| {
+            let mut foreground = None;
+            let mut background = None;
+            let mut shown = Vec::new();
+            let mut chars = line.chars();
+            while let Some(ch) = chars.next() {
+                if ch != '\x1b' {
+                    shown.push((ch, foreground, background));
+                    continue;
+                }
+                assert_eq!(chars.next(), Some('['));
+                let codes: String = chars.by_ref().take_while(|&c| c != 'm').collect();
+                let codes: Vec<u8> = codes.split(';').map(|code| code.parse().unwrap()).collect();
+                let mut codes = codes.as_slice();
+                while let [code, rest @ ..] = codes {
+                    codes = rest;
+                    match code {
+                        0 => (foreground, background) = (None, None),
+                        39 => foreground = None,
+                        49 => background = None,
+                        38 | 48 => {
+                            let [2, r, g, b, rest @ ..] = codes else {
+                                panic!("unexpected color code in {line:?}");
+                            };
+                            let color = Some(Rgb([*r, *g, *b]));
+                            if *code == 38 {
+                                foreground = color;
+                            } else {
+                                background = color;
+                            }
+                            codes = rest;
+                        }
+                        _ => panic!("unexpected code {code} in {line:?}"),
+                    }
+                }
+            }
+            // Everything must be reset at the end of the line
+            assert_eq!(
+                (foreground, background),
+                (None, None),
+                "colors leak out of {line:?}"
+            );
+            shown
+        })
+        .collect()
+}
+
+/// The foreground of a space cannot be seen, so it is left out of the comparison.
+fn visible(shown: Vec<Vec<Shown>>) -> Vec<Vec<Shown>> {
+    shown
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|(ch, foreground, background)| match ch {
+                    ' ' => (ch, None, background),
+                    _ => (ch, foreground, background),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Length of the output if every character had its own escape codes and reset.
+fn one_code_per_character(cells: &[Vec<Cell>]) -> usize {
+    cells
+        .iter()
+        .flatten()
+        .map(|cell| {
+            let codes: Vec<String> = [(cell.foreground, 38), (cell.background, 48)]
+                .into_iter()
+                .filter_map(|(color, layer)| {
+                    let Rgb([r, g, b]) = color?;
+                    Some(format!("{layer};2;{r};{g};{b}"))
+                })
+                .collect();
+            match codes.is_empty() {
+                true => cell.ch.len_utf8(),
+                false => format!("\x1b[{}m{}\x1b[0m", codes.join(";"), cell.ch).len(),
+            }
+        })
+        .sum::<usize>()
+        + cells.len()
+}
+
+#[test]
+fn compact_output_looks_the_same_and_is_smaller() {
+    for mode in [
+        Mode::Edges,
+        Mode::Ramp,
+        Mode::Braille,
+        Mode::Halfblock,
+        Mode::Quadrant,
+    ] {
+        let renderer = AsciiRenderer::builder().mode(mode).build();
+        let image = picture();
+        let cells = renderer.cells(&image);
+        let output = renderer.render(&image);
+
+        let expected: Vec<Vec<Shown>> = cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| (cell.ch, cell.foreground, cell.background))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(visible(interpret(&output)), visible(expected), "{mode:?}");
+
+        let naive = one_code_per_character(&cells);
+        assert!(
+            output.len() < naive,
+            "{mode:?}: {} bytes is not smaller than {naive}",
+            output.len()
+        );
+    }
+}
+
+#[test]
+fn flat_areas_need_one_code_per_row() {
+    let flat = DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 10, Rgba([10, 20, 30, 255])));
+    let renderer = AsciiRenderer::builder().fatness(1.0).build();
+    let output = renderer.render(&flat);
+
+    let row = format!(
+        "\x1b[38;2;10;20;30m{}\x1b[0m\n",
+        renderer.cells(&flat)[0]
+            .iter()
+            .map(|cell| cell.ch)
+            .collect::<String>()
+    );
+    assert_eq!(output, row.repeat(10));
+    assert!(output.len() * 10 < one_code_per_character(&renderer.cells(&flat)));
+}
+
+#[test]
+fn tolerance_reuses_close_colors() {
+    let gradient = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 1, |x, _| {
+        Rgba([x as u8, 100, 100, 255])
+    }));
+    let exact = AsciiRenderer::builder()
+        .mode(Mode::Ramp)
+        .fatness(1.0)
+        .build();
+    let tolerant = AsciiRenderer::builder()
+        .mode(Mode::Ramp)
+        .fatness(1.0)
+        .color_tolerance(8)
+        .build();
+
+    let exact = exact.render(&gradient);
+    let tolerant = tolerant.render(&gradient);
+    // One code per color and the reset at the end of the line
+    assert_eq!(exact.matches('\x1b').count(), 64 + 1);
+    // Red 0, 9, 18, ..., 63: each color is reused for the next 8 characters
+    assert_eq!(tolerant.matches('\x1b').count(), 8 + 1);
+}
@@ -0,0 +1,53 @@
+//! The neighbourhood of a pixel must be read the right way up, and must end at the image border.
+
+use ascii_renderer::edges::{Distance, FULL, pattern};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+const INK: Rgba<u8> = Rgba([200, 30, 30, 255]);
+const PAPER: Rgba<u8> = Rgba([20, 60, 200, 255]);
+
+/// An image with ink where `rows` has a `#` and paper everywhere else.
+fn image(rows: &[&str]) -> DynamicImage {
+    let width = rows[0].len() as u32;
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(
+        width,
+        rows.len() as u32,
+        |x, y| match rows[y as usize].as_bytes()[x as usize] {
+            b'#' => INK,
+            _ => PAPER,
+        },
+    ))
+}
+
+fn pattern_at(image: &DynamicImage, x: u32, y: u32) -> u8 {
+    let distance = Distance::Rgba;
+    pattern(image, x, y, distance, distance.default_threshold())
+}
+
+#[test]
+fn only_the_neighbour_below_is_similar() {
+    let image = image(&["...", ".#.", ".#."]);
+    // The bottom-right corner of the neighbourhood: the pixel and the one below it
+    assert_eq!(pattern_at(&image, 1, 1), 0b1001);
+}
+
+#[test]
+fn neighbours_below_are_not_mistaken_for_neighbours_above() {
+    let below = image(&["...", ".#.", ".##"]);
+    assert_eq!(pattern_at(&below, 1, 1), 0b1011);
+
+    let above = image(&[".##", ".#.", "..."]);
+    assert_eq!(pattern_at(&above, 1, 1), 0b1101);
+}
+
+#[test]
+fn pixels_outside_the_image_are_not_similar() {
+    // A single row: nothing above or below, so only the top edge is left
+    let row = image(&["###"]);
+    assert_eq!(pattern_at(&row, 1, 0), 0b1100);
+
+    // In the corner of a flat image only the inner 2x2 block is similar
+    let flat = image(&["###", "###", "###"]);
+    assert_eq!(pattern_at(&flat, 0, 0), FULL);
+    assert_eq!(pattern_at(&flat, 2, 2), FULL);
+}
@@ -0,0 +1,55 @@
+use std::{env, ffi::OsString, fs, path::PathBuf};
+
+/// Returns the command line with the config file's default arguments spliced in
+/// right after the program name, so anything given on the command line wins,
+/// together with the config file's path if any defaults were taken from it.
+///
+/// The file holds one argument per line (e.g. `--smart-case` or
+/// `--color=always`); blank lines and lines starting with `#` are ignored.
+/// Passing `--no-config` skips it.
+pub fn merged_args() -> (Vec<OsString>, Option<PathBuf>) {
+    let mut args: Vec<OsString> = env::args_os().collect();
+    if args.iter().skip(1).any(|arg| arg == "--no-config") {
+        return (args, None);
+    }
+    let Some((path, explicit)) = config_path() else {
+        return (args, None);
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            // A missing default config is normal; a missing explicit one is worth a warning
+            if explicit {
+                eprintln!("Error reading config file '{}': {}", path.display(), e);
+            }
+            return (args, None);
+        }
+    };
+
+    let defaults = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(OsString::from)
+        .collect::<Vec<_>>();
+    if defaults.is_empty() {
+        return (args, None);
+    }
+    args.splice(1..1, defaults);
+    (args, Some(path))
+}
+
+/// `$PREG_CONFIG_PATH` if set (an empty value disables the config), otherwise
+/// `$XDG_CONFIG_HOME/preg/config` or `~/.config/preg/config`.
+///
+/// The flag says whether the path was given explicitly.
+fn config_path() -> Option<(PathBuf, bool)> {
+    if let Some(path) = env::var_os("PREG_CONFIG_PATH") {
+        return (!path.is_empty()).then(|| (PathBuf::from(path), true));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some((base.join("preg").join("config"), false))
+}
@@ -0,0 +1,55 @@
+use std::{env, fs, path::Path};
+
+/// Builds OSC 8 terminal hyperlinks from a `--hyperlink-format` template.
+///
+/// Supported placeholders are `{path}` (absolute, percent-encoded), `{line}`,
+/// `{column}` and `{host}`.
+pub struct HyperlinkFormat {
+    template: String,
+    host: String,
+}
+
+impl HyperlinkFormat {
+    pub fn new(template: &str) -> Self {
+        HyperlinkFormat {
+            template: template.to_string(),
+            host: hostname(),
+        }
+    }
+
+    /// Returns the URL for a location; missing line/column numbers default to 1.
+    pub fn url(&self, path: &Path, line: Option<usize>, column: Option<usize>) -> String {
+        self.template
+            .replace("{path}", &percent_encode(&path.to_string_lossy()))
+            .replace("{line}", &line.unwrap_or(1).to_string())
+            .replace("{column}", &column.unwrap_or(1).to_string())
+            .replace("{host}", &self.host)
+    }
+}
+
+/// Wraps `text` in an OSC 8 escape so terminals make it clickable.
+pub fn link(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+fn hostname() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Escapes everything except unreserved URL characters and path separators.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,400 @@
+use atty::Stream;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::matcher::{Match, Matcher};
+use crate::preprocess::Preprocessor;
+use crate::{find_hits, highlight, ignore_case, Cli};
+
+/// Results beyond this are not collected, to keep typing responsive on big trees.
+const MAX_RESULTS: usize = 10_000;
+/// Lines shown above and below the selected match in the preview pane.
+const CONTEXT: usize = 3;
+const PROMPT: &str = "preg> ";
+
+/// An input loaded into memory so it can be searched again on every keystroke.
+struct Document {
+    label: String,
+    path: Option<PathBuf>,
+    lines: Vec<String>,
+}
+
+/// A matching line, pointing into the loaded documents.
+struct Row {
+    document: usize,
+    line: usize,
+    hits: Vec<Match>,
+}
+
+struct State {
+    query: String,
+    results: Vec<Row>,
+    truncated: bool,
+    selected: usize,
+    scroll: usize,
+}
+
+/// What the user picked before leaving the UI.
+enum Choice {
+    Quit,
+    Print(usize),
+    Edit(usize),
+}
+
+/// Raw mode and the alternate screen, restored on drop (including on errors).
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), terminal::LeaveAlternateScreen, cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs `--interactive`: type a pattern, browse live results, then print or edit the selection.
+pub fn run(
+    args: &Cli,
+    inputs: &[Option<PathBuf>],
+    preprocessor: Option<&Preprocessor>,
+    use_color: bool,
+) -> io::Result<()> {
+    let documents = load(inputs, preprocessor)?;
+    let mut state = State {
+        query: args.pattern.clone().unwrap_or_default(),
+        results: Vec::new(),
+        truncated: false,
+        selected: 0,
+        scroll: 0,
+    };
+    state.refresh(&documents, args);
+
+    let choice = {
+        let _screen = Screen::enter()?;
+        event_loop(&mut state, &documents, args, use_color)?
+    };
+
+    match choice {
+        Choice::Quit => {}
+        Choice::Print(index) => {
+            let result = &state.results[index];
+            let document = &documents[result.document];
+            let line = &document.lines[result.line];
+            let text = if use_color {
+                highlight(line, &result.hits)
+            } else {
+                line.clone()
+            };
+            println!("{}:{}:{}", document.label, result.line + 1, text);
+        }
+        Choice::Edit(index) => {
+            let result = &state.results[index];
+            let document = &documents[result.document];
+            let Some(path) = &document.path else {
+                eprintln!("Cannot open standard input in an editor.");
+                return Ok(());
+            };
+            open_editor(path, result.line + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads every input up front; stdin is read to the end before the UI takes over the terminal.
+fn load(
+    inputs: &[Option<PathBuf>],
+    preprocessor: Option<&Preprocessor>,
+) -> io::Result<Vec<Document>> {
+    let mut documents = Vec::new();
+    for input in inputs {
+        let pre =
+            preprocessor.filter(|pre| input.as_ref().is_some_and(|path| pre.applies_to(path)));
+        let (label, bytes) = match (input, pre) {
+            (Some(path), Some(pre)) => match pre.run(path) {
+                Ok(bytes) => (path.display().to_string(), bytes),
+                Err(e) => {
+                    eprintln!("Error preprocessing file '{}': {}", path.display(), e);
+                    continue;
+                }
+            },
+            (Some(path), None) => match fs::read(path) {
+                Ok(bytes) => (path.display().to_string(), bytes),
+                Err(e) => {
+                    eprintln!("Error opening file '{}': {}", path.display(), e);
+                    continue;
+                }
+            },
+            (None, _) => {
+                // The UI needs the terminal, so the input can't come from it too
+                if atty::is(Stream::Stdin) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "No file specified and no input piped to stdin.",
+                    ));
+                }
+                let mut bytes = Vec::new();
+                io::stdin().read_to_end(&mut bytes)?;
+                ("(standard input)".to_string(), bytes)
+            }
+        };
+
+        let lines = String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(|line| line.replace('\t', " "))
+            .collect();
+        documents.push(Document {
+            label,
+            path: input.clone(),
+            lines,
+        });
+    }
+    Ok(documents)
+}
+
+impl State {
+    /// Re-runs the search for the current query with the same matcher as batch mode.
+    fn refresh(&mut self, documents: &[Document], args: &Cli) {
+        let matcher = Matcher::new(&self.query, ignore_case(args, &self.query), args.fuzzy);
+        self.results.clear();
+        self.truncated = false;
+        self.selected = 0;
+        self.scroll = 0;
+
+        for (document_index, document) in documents.iter().enumerate() {
+            for (line_index, line) in document.lines.iter().enumerate() {
+                let hits = find_hits(&matcher, line, args.line_regexp);
+                if hits.is_empty() == args.invert_match {
+                    if self.results.len() == MAX_RESULTS {
+                        self.truncated = true;
+                        return;
+                    }
+                    self.results.push(Row {
+                        document: document_index,
+                        line: line_index,
+                        hits,
+                    });
+                }
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.results.is_empty() {
+            return;
+        }
+        let last = self.results.len() - 1;
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+}
+
+fn event_loop(
+    state: &mut State,
+    documents: &[Document],
+    args: &Cli,
+    use_color: bool,
+) -> io::Result<Choice> {
+    loop {
+        draw(state, documents, use_color)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let page = list_height(terminal::size()?.1) as isize;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Esc => return Ok(Choice::Quit),
+            KeyCode::Char('c') if ctrl => return Ok(Choice::Quit),
+            KeyCode::Char('o') if ctrl && !state.results.is_empty() => {
+                return Ok(Choice::Edit(state.selected))
+            }
+            KeyCode::Enter if !state.results.is_empty() => {
+                return Ok(Choice::Print(state.selected))
+            }
+            KeyCode::Up => state.move_selection(-1),
+            KeyCode::Down => state.move_selection(1),
+            KeyCode::PageUp => state.move_selection(-page),
+            KeyCode::PageDown => state.move_selection(page),
+            KeyCode::Home => state.move_selection(isize::MIN),
+            KeyCode::End => state.move_selection(isize::MAX),
+            KeyCode::Backspace => {
+                state.query.pop();
+                state.refresh(documents, args);
+            }
+            KeyCode::Char(c) if !ctrl => {
+                state.query.push(c);
+                state.refresh(documents, args);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rows available for the result list: everything but the prompt, status line and preview.
+fn list_height(rows: u16) -> usize {
+    (rows as usize).saturating_sub(2 * CONTEXT + 4).max(1)
+}
+
+fn draw(state: &mut State, documents: &[Document], use_color: bool) -> io::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let width = columns as usize;
+    let height = list_height(rows);
+
+    // Keep the selection on screen
+    if state.selected < state.scroll {
+        state.scroll = state.selected;
+    } else if state.selected >= state.scroll + height {
+        state.scroll = state.selected + 1 - height;
+    }
+
+    let mut out = io::stdout();
+    queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+    queue!(
+        out,
+        Print(fit(
+            &format!("{}{}", PROMPT, state.query),
+            &[],
+            width,
+            false
+        ))
+    )?;
+
+    for (row, index) in (state.scroll..state.results.len()).take(height).enumerate() {
+        let result = &state.results[index];
+        let document = &documents[result.document];
+        let marker = if index == state.selected { "> " } else { "  " };
+        let prefix = format!("{}{}:{}:", marker, document.label, result.line + 1);
+        // Shift the hits so they still line up after the prefix is prepended
+        let hits: Vec<Match> = result
+            .hits
+            .iter()
+            .map(|hit| Match {
+                start: hit.start + prefix.len(),
+                end: hit.end + prefix.len(),
+                ..*hit
+            })
+            .collect();
+        let text = format!("{}{}", prefix, document.lines[result.line]);
+        queue!(
+            out,
+            cursor::MoveTo(0, row as u16 + 1),
+            Print(fit(&text, &hits, width, use_color))
+        )?;
+    }
+
+    let status = match state.results.len() {
+        _ if state.truncated => format!(
+            "{}+ matching lines (showing the first {})",
+            MAX_RESULTS, MAX_RESULTS
+        ),
+        1 => "1 matching line".to_string(),
+        n => format!("{} matching lines", n),
+    };
+    let help = "  [Enter] print  [Ctrl-O] open in $EDITOR  [Esc] quit";
+    queue!(
+        out,
+        cursor::MoveTo(0, height as u16 + 1),
+        Print(fit(&format!("{}{}", status, help), &[], width, false))
+    )?;
+    queue!(
+        out,
+        cursor::MoveTo(0, height as u16 + 2),
+        Print("─".repeat(width))
+    )?;
+
+    if let Some(result) = state.results.get(state.selected) {
+        let document = &documents[result.document];
+        let first = result.line.saturating_sub(CONTEXT);
+        let last = (result.line + CONTEXT).min(document.lines.len() - 1);
+        let number_width = (last + 1).to_string().len();
+        for (row, line_index) in (first..=last).enumerate() {
+            let line = &document.lines[line_index];
+            let separator = if line_index == result.line { ':' } else { '-' };
+            let prefix = format!("{:>w$}{} ", line_index + 1, separator, w = number_width);
+            let hits: Vec<Match> = if line_index == result.line {
+                result
+                    .hits
+                    .iter()
+                    .map(|hit| Match {
+                        start: hit.start + prefix.len(),
+                        end: hit.end + prefix.len(),
+                        ..*hit
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let text = format!("{}{}", prefix, line);
+            queue!(
+                out,
+                cursor::MoveTo(0, (height + 3 + row) as u16),
+                Print(fit(&text, &hits, width, use_color))
+            )?;
+        }
+    }
+
+    let cursor_column = (PROMPT.len() + state.query.chars().count()).min(width.saturating_sub(1));
+    queue!(out, cursor::MoveTo(cursor_column as u16, 0), cursor::Show)?;
+    out.flush()
+}
+
+/// Cuts `text` to `width` characters and highlights the hits that remain visible.
+fn fit(text: &str, hits: &[Match], width: usize, use_color: bool) -> String {
+    let cut = text
+        .char_indices()
+        .nth(width)
+        .map_or(text.len(), |(index, _)| index);
+    let text = &text[..cut];
+    if !use_color {
+        return text.to_string();
+    }
+
+    let visible: Vec<Match> = hits
+        .iter()
+        .filter(|hit| hit.start < cut)
+        .map(|hit| Match {
+            end: hit.end.min(cut),
+            ..*hit
+        })
+        .collect();
+    highlight(text, &visible)
+}
+
+/// Opens `$VISUAL`/`$EDITOR` (falling back to `vi`) at `line` using the common `+LINE` argument.
+fn open_editor(path: &Path, line: usize) -> io::Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "$EDITOR is empty"));
+    };
+
+    Command::new(program)
+        .args(parts)
+        .arg(format!("+{}", line))
+        .arg(path)
+        .status()?;
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use serde_json::Value;
+
+use crate::find_hits;
+use crate::matcher::{Match, Matcher};
+
+/// A path into a JSON document such as `.request.path` or `.items[0].id`.
+#[derive(Debug, Clone)]
+pub struct FieldPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A `--where` condition: `path=value` or `path!=value`.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    path: FieldPath,
+    value: String,
+    negated: bool,
+}
+
+/// How a line fares once it has been parsed as JSON.
+pub enum Selection {
+    /// The line is JSON and passes every `--where`; hits are offsets into the raw line
+    Hits(Vec<Match>),
+    /// The line is JSON but a `--where` condition rejected it
+    Filtered,
+    /// The line is not JSON at all
+    NotJson,
+}
+
+/// Parses a `--json-field` argument. The leading dot is optional.
+pub fn parse_field_path(value: &str) -> Result<FieldPath, String> {
+    let mut segments = Vec::new();
+    for part in value.strip_prefix('.').unwrap_or(value).split('.') {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        while let Some(inner) = rest.strip_prefix('[') {
+            let close = inner
+                .find(']')
+                .ok_or_else(|| format!("missing ']' in '{}'", value))?;
+            let index = inner[..close].parse().map_err(|_| {
+                format!("'{}' is not an array index in '{}'", &inner[..close], value)
+            })?;
+            segments.push(Segment::Index(index));
+            rest = &inner[close + 1..];
+        }
+        if !rest.is_empty() {
+            return Err(format!("unexpected '{}' in '{}'", rest, value));
+        }
+    }
+    if segments.is_empty() {
+        return Err(format!("'{}' does not name a field", value));
+    }
+    Ok(FieldPath { segments })
+}
+
+/// Parses a `--where` argument such as `level=error` or `.status!=200`.
+pub fn parse_condition(value: &str) -> Result<Condition, String> {
+    let (path, expected, negated) = match value.split_once("!=") {
+        Some((path, expected)) => (path, expected, true),
+        None => match value.split_once('=') {
+            Some((path, expected)) => (path, expected, false),
+            None => {
+                return Err(format!(
+                    "expected FIELD=VALUE or FIELD!=VALUE, got '{}'",
+                    value
+                ))
+            }
+        },
+    };
+    Ok(Condition {
+        path: parse_field_path(path)?,
+        value: expected.to_string(),
+        negated,
+    })
+}
+
+impl FieldPath {
+    fn lookup<'v>(&self, root: &'v Value) -> Option<&'v Value> {
+        self.segments
+            .iter()
+            .try_fold(root, |value, segment| match segment {
+                Segment::Key(key) => value.get(key),
+                Segment::Index(index) => value.get(index),
+            })
+    }
+}
+
+/// Parses `line` as JSON and matches the pattern against the selected fields only.
+///
+/// With no `fields`, the whole raw line is matched, so `--where` can be used on
+/// its own as a pre-filter. Hits are mapped back into the raw line so they can
+/// be highlighted in place.
+pub fn select(
+    line: &str,
+    fields: &[FieldPath],
+    conditions: &[Condition],
+    matcher: &Matcher,
+    line_regexp: bool,
+) -> Selection {
+    let Ok(root) = serde_json::from_str::<Value>(line) else {
+        return Selection::NotJson;
+    };
+
+    for condition in conditions {
+        let equal = condition
+            .path
+            .lookup(&root)
+            .is_some_and(|value| text_of(value) == condition.value);
+        if equal == condition.negated {
+            return Selection::Filtered;
+        }
+    }
+
+    if fields.is_empty() {
+        return Selection::Hits(find_hits(matcher, line, line_regexp));
+    }
+
+    let mut hits = Vec::new();
+    for field in fields {
+        let Some(value) = field.lookup(&root) else {
+            continue;
+        };
+        let text = text_of(value);
+        let value_hits = find_hits(matcher, &text, line_regexp);
+        if value_hits.is_empty() {
+            continue;
+        }
+        hits.extend(locate(line, field, value, &value_hits));
+    }
+    hits.sort_by_key(|hit| hit.start);
+    hits.dedup_by(|later, earlier| later.start < earlier.end);
+    Selection::Hits(hits)
+}
+
+/// The text a pattern is matched against: strings unquoted, anything else as JSON.
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Finds where the selected `field`'s `value` is written in the raw line and translates `hits` there.
+///
+/// When the value was written with escapes (so offsets in the decoded string
+/// don't line up with the raw text), the whole value is highlighted instead. If
+/// the value can't be found verbatim (unusual formatting), or is written in
+/// more than one place the field could be (so the wrong copy might be
+/// highlighted), the hits are kept but point at the start of the line with
+/// zero width, so the line still counts as a match without highlighting
+/// anything misleading.
+fn locate(line: &str, field: &FieldPath, value: &Value, hits: &[Match]) -> Vec<Match> {
+    let encoded = value.to_string();
+    // Only accept an occurrence in the field's position: after its own key in an
+    // object, after ',' or '[' for an array element
+    let key = match field.segments.last() {
+        Some(Segment::Key(key)) => Some(Value::from(key.as_str()).to_string()),
+        _ => None,
+    };
+    let candidates: Vec<usize> = line
+        .match_indices(&encoded)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            let before = line[..start].trim_end();
+            match &key {
+                Some(key) => before
+                    .strip_suffix(':')
+                    .is_some_and(|before| before.trim_end().ends_with(key.as_str())),
+                None => before.ends_with([',', '[']),
+            }
+        })
+        .collect();
+
+    let [start] = candidates[..] else {
+        return hits
+            .iter()
+            .map(|hit| Match {
+                start: 0,
+                end: 0,
+                ..*hit
+            })
+            .collect();
+    };
+
+    match value {
+        Value::String(s) if encoded.len() == s.len() + 2 => hits
+            .iter()
+            .map(|hit| Match {
+                start: start + 1 + hit.start,
+                end: start + 1 + hit.end,
+                ..*hit
+            })
+            .collect(),
+        _ => {
+            let distance = hits.iter().map(|hit| hit.distance).min().unwrap_or(0);
+            vec![Match {
+                start,
+                end: start + encoded.len(),
+                distance,
+            }]
+        }
+    }
+}
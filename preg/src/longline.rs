@@ -0,0 +1,365 @@
+use std::io::{self, BufRead};
+
+use crate::find_hits;
+use crate::matcher::{Match, Matcher};
+
+/// Lines are buffered up to at least this many bytes before switching to chunked matching.
+const CHUNK: usize = 64 * 1024;
+
+/// What is printed in place of an overlong line: the text around its hits.
+pub struct Excerpt {
+    pub text: String,
+    /// Hits as offsets into `text`
+    pub hits: Vec<Match>,
+}
+
+/// An overlong line that was matched chunk by chunk instead of being read whole.
+pub struct Streamed {
+    pub excerpt: Excerpt,
+    /// The line's terminator (`\r\n` with `--crlf`), empty at the end of the input
+    pub ending: Vec<u8>,
+    /// Bytes read past the start of the line handed to `scan`
+    pub read: u64,
+}
+
+/// How many bytes of a line to buffer before it is treated as overlong and streamed.
+pub fn buffer_size(max_columns: usize) -> usize {
+    // Enough that the kept start of the line is itself over the limit
+    max_columns.saturating_add(4).max(CHUNK)
+}
+
+/// Cuts `line` down to an excerpt when it is longer than `max_columns` bytes.
+///
+/// With `preview`, the excerpt keeps `max_columns / 2` bytes of context on
+/// each side of every hit (or the start of the line if there are none);
+/// without it, only the hits themselves are kept, for `-o` and counting.
+pub fn shorten(
+    line: &str,
+    hits: &[Match],
+    max_columns: Option<usize>,
+    preview: bool,
+) -> Option<Excerpt> {
+    let max_columns = max_columns.filter(|&max| line.len() > max)?;
+    let mut builder = Builder::new(max_columns, preview);
+    for &hit in hits {
+        builder.add(line, 0, hit);
+    }
+    Some(builder.finish(line, line.len()))
+}
+
+/// Like `read_until`, but stops once `buf` holds `limit` bytes.
+///
+/// Returns the bytes read and whether the line was read to its end.
+pub fn read_line(
+    reader: &mut impl BufRead,
+    terminator: u8,
+    buf: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<(usize, bool)> {
+    let mut read = 0;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok((read, true));
+        }
+        let window = &available[..available.len().min(limit.saturating_sub(buf.len()))];
+        let (used, done) = match window.iter().position(|&byte| byte == terminator) {
+            Some(index) => (index + 1, true),
+            None => (window.len(), false),
+        };
+        buf.extend_from_slice(&window[..used]);
+        reader.consume(used);
+        read += used;
+        if done {
+            return Ok((read, true));
+        }
+        if buf.len() >= limit {
+            return Ok((read, false));
+        }
+    }
+}
+
+/// Matches the rest of an overlong line in chunks, so memory stays bounded by the chunk size.
+///
+/// `buf` holds the start of the line on entry and keeps only that start (cut
+/// to a char boundary) on return, so the caller can still read a timestamp
+/// from it. Consecutive chunks overlap by twice the longest possible hit plus
+/// its context, so nothing is lost at a boundary (fuzzy hits that overlap each
+/// other may still be grouped a little differently there). Returns `None`,
+/// with the rest of the line skipped, when the line is not valid UTF-8.
+pub fn scan(
+    reader: &mut impl BufRead,
+    buf: &mut Vec<u8>,
+    terminator: u8,
+    crlf: bool,
+    matcher: &Matcher,
+    max_columns: usize,
+    preview: bool,
+) -> io::Result<Option<Streamed>> {
+    let mut builder = Builder::new(max_columns, preview);
+    let carry = builder.radius + 2 * matcher.max_match_len();
+    let chunk_size = buf.len().max(2 * (carry + builder.radius) + 8);
+
+    let head_len = match std::str::from_utf8(buf) {
+        Ok(head) => head.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => {
+            skip_line(reader, terminator, chunk_size)?;
+            return Ok(None);
+        }
+    };
+    let mut pending = buf.clone();
+    buf.truncate(head_len);
+
+    let mut read = 0;
+    let mut ending = Vec::new();
+    // Line offset of `pending[0]`, and where in `pending` new hits may start
+    let mut offset = 0;
+    let mut from = 0;
+    loop {
+        let (more, complete) = read_line(reader, terminator, &mut pending, chunk_size)?;
+        read += more as u64;
+        if complete && pending.last() == Some(&terminator) {
+            pending.pop();
+            ending.push(terminator);
+            if crlf && terminator == b'\n' && pending.last() == Some(&b'\r') {
+                pending.pop();
+                ending.insert(0, b'\r');
+            }
+        }
+
+        let text = match std::str::from_utf8(&pending) {
+            Ok(text) => text,
+            // A char split by the chunk boundary is completed by the next read
+            Err(e) if e.error_len().is_none() && !complete => {
+                std::str::from_utf8(&pending[..e.valid_up_to()]).unwrap_or_default()
+            }
+            Err(_) => {
+                if !complete {
+                    skip_line(reader, terminator, chunk_size)?;
+                }
+                return Ok(None);
+            }
+        };
+
+        // Hits starting past the cutoff are left for the next chunk, where their context fits
+        let cutoff = if complete {
+            text.len()
+        } else {
+            text.floor_char_boundary(text.len().saturating_sub(carry))
+        };
+        let mut next_from = cutoff;
+        for hit in find_hits(matcher, text, false) {
+            if hit.start >= from && hit.start < cutoff {
+                builder.add(text, offset, hit);
+                next_from = next_from.max(hit.end);
+            }
+        }
+
+        if complete {
+            let excerpt = builder.finish(&String::from_utf8_lossy(buf), offset + text.len());
+            return Ok(Some(Streamed {
+                excerpt,
+                ending,
+                read,
+            }));
+        }
+
+        // Keep the context before the cutoff for hits found in the next chunk
+        let keep = text.floor_char_boundary(cutoff.saturating_sub(builder.radius));
+        pending.drain(..keep);
+        offset += keep;
+        from = next_from - keep;
+    }
+}
+
+/// Reads and discards the rest of the current line.
+fn skip_line(reader: &mut impl BufRead, terminator: u8, chunk_size: usize) -> io::Result<()> {
+    let mut scratch = Vec::new();
+    loop {
+        scratch.clear();
+        if read_line(reader, terminator, &mut scratch, chunk_size)?.1 {
+            return Ok(());
+        }
+    }
+}
+
+/// Collects the windows around hits into an excerpt, merging windows that touch.
+struct Builder {
+    max_columns: usize,
+    /// Bytes of context kept on each side of a hit
+    radius: usize,
+    excerpt: Excerpt,
+    /// Line offset where the excerpt text currently ends
+    end: Option<usize>,
+}
+
+impl Builder {
+    fn new(max_columns: usize, preview: bool) -> Self {
+        Builder {
+            max_columns,
+            radius: if preview { max_columns / 2 } else { 0 },
+            excerpt: Excerpt {
+                text: String::new(),
+                hits: Vec::new(),
+            },
+            end: None,
+        }
+    }
+
+    /// Adds `hit`, found in `chunk`, which starts `offset` bytes into the line.
+    fn add(&mut self, chunk: &str, offset: usize, hit: Match) {
+        let start = chunk.floor_char_boundary(hit.start.saturating_sub(self.radius));
+        let end = chunk.ceil_char_boundary(hit.end.saturating_add(self.radius).min(chunk.len()));
+        let text = &mut self.excerpt.text;
+        match self.end {
+            Some(last) if offset + start <= last => {
+                if offset + end > last {
+                    text.push_str(&chunk[last - offset..end]);
+                    self.end = Some(offset + end);
+                }
+            }
+            previous => {
+                if previous.is_some() {
+                    text.push_str(" [...] ");
+                } else if offset + start > 0 {
+                    text.push_str("[...] ");
+                }
+                text.push_str(&chunk[start..end]);
+                self.end = Some(offset + end);
+            }
+        }
+
+        let behind = self.end.unwrap_or_default() - (offset + hit.start);
+        let start = text.len() - behind;
+        self.excerpt.hits.push(Match {
+            start,
+            end: start + (hit.end - hit.start),
+            ..hit
+        });
+    }
+
+    /// Finishes the excerpt of a line `len` bytes long that starts with `head`.
+    fn finish(mut self, head: &str, len: usize) -> Excerpt {
+        let text = &mut self.excerpt.text;
+        match self.end {
+            // Nothing matched: preview the start of the line instead
+            None => text.push_str(&head[..head.floor_char_boundary(self.max_columns)]),
+            Some(end) if end >= len => return self.excerpt,
+            Some(_) => {}
+        }
+        text.push_str(" [...]");
+        self.excerpt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    /// Bytes of the line read before `scan` takes over, small so lines span many chunks.
+    const HEAD: usize = 16;
+
+    /// Streams `line` (followed by another line) through `scan`, checking
+    /// that exactly the line and its terminator were consumed.
+    fn streamed(
+        line: &[u8],
+        matcher: &Matcher,
+        max_columns: usize,
+        preview: bool,
+    ) -> Option<(String, Vec<Match>)> {
+        let mut input = line.to_vec();
+        input.extend_from_slice(b"\nnext\n");
+        let mut reader = Cursor::new(input);
+        let mut buf = Vec::new();
+        read_line(&mut reader, b'\n', &mut buf, HEAD).unwrap();
+        let streamed = scan(
+            &mut reader,
+            &mut buf,
+            b'\n',
+            false,
+            matcher,
+            max_columns,
+            preview,
+        )
+        .unwrap()
+        .map(|streamed| {
+            assert_eq!(streamed.ending, b"\n");
+            assert_eq!(streamed.read as usize, line.len() + 1 - HEAD);
+            (streamed.excerpt.text, streamed.excerpt.hits)
+        });
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "next\n");
+        streamed
+    }
+
+    /// The excerpt of `line` when it is matched whole.
+    fn whole(
+        line: &str,
+        matcher: &Matcher,
+        max_columns: usize,
+        preview: bool,
+    ) -> (String, Vec<Match>) {
+        let hits = find_hits(matcher, line, false);
+        let excerpt = shorten(line, &hits, Some(max_columns), preview).unwrap();
+        (excerpt.text, excerpt.hits)
+    }
+
+    /// Checks that streaming `line` gives the same excerpt as matching it whole.
+    fn check(line: &str, matcher: &Matcher) {
+        for preview in [false, true] {
+            assert_eq!(
+                streamed(line.as_bytes(), matcher, 10, preview),
+                Some(whole(line, matcher, 10, preview)),
+                "preview: {}, line: {:?}",
+                preview,
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn hits_across_chunk_boundaries_are_found_once() {
+        let matcher = Matcher::new("needle", false, None);
+        for at in 0..400 {
+            let line = format!("{}needle{}", "a".repeat(at), "b".repeat(400 - at));
+            check(&line, &matcher);
+        }
+        let line = format!("{0}needle{0}needle{0}", "x".repeat(97));
+        check(&line, &matcher);
+    }
+
+    #[test]
+    fn chars_split_across_chunks_are_kept_whole() {
+        let matcher = Matcher::new("needle", false, None);
+        for at in 0..120 {
+            let line = format!("{}needle{}", "é日".repeat(at), "日é".repeat(120 - at));
+            check(&line, &matcher);
+        }
+    }
+
+    #[test]
+    fn fuzzy_hits_across_chunk_boundaries_are_found() {
+        let matcher = Matcher::new("needle", false, Some(1));
+        for at in 0..400 {
+            let line = format!("{}neddle{}", "a".repeat(at), "b".repeat(400 - at));
+            check(&line, &matcher);
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_skips_the_line() {
+        let matcher = Matcher::new("needle", false, None);
+        for at in [3, HEAD + 5, 300] {
+            let mut line = format!("{}needle{}", "a".repeat(at), "b".repeat(400)).into_bytes();
+            line[at] = 0xff;
+            assert_eq!(streamed(&line, &matcher, 10, false), None, "at: {}", at);
+        }
+    }
+}
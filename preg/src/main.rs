@@ -1,41 +1,190 @@
+use chrono::{DateTime, Utc};
 use clap::Parser;
-use std::{fs::File, io::{self, BufRead, BufReader}, process};
+use std::{fs::{self, File}, io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}, process, time::Instant};
 use atty::Stream;
 
+mod config;
+mod hyperlink;
+mod interactive;
+mod json;
+mod longline;
+mod matcher;
+mod preprocess;
+mod stats;
+mod table;
+mod timerange;
+use hyperlink::HyperlinkFormat;
+use preprocess::Preprocessor;
+use matcher::{Match, Matcher};
+use stats::Stats;
+
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct CLI {
+#[command(author, version, about, long_about = None, args_override_self = true)]
+struct Cli {
     /// The pattern to search for
-    #[arg(short, long, value_name = "PATTERN", aliases = ["p"])]
-    pattern: String,
+    #[arg(short, long, value_name = "PATTERN", aliases = ["p"], required_unless_present = "interactive")]
+    pattern: Option<String>,
 
-    /// The path to the file to search in (use "-" for stdin)
-    #[arg(short, long, value_name = "FILE", aliases = ["f"])]
-    filename: Option<String>,
+    /// The files or directories to search in (use "-" for stdin); directories are searched recursively
+    #[arg(short, long, value_name = "FILE", aliases = ["f"], num_args = 1..)]
+    filename: Vec<String>,
 
     /// Case insensitive search
-    #[arg(short, long, aliases = ["i"])]
+    #[arg(short, long, aliases = ["i"], overrides_with_all = ["smart_case", "case_sensitive"])]
     ignore_case: bool,
 
+    /// Case insensitive search, unless the pattern contains an uppercase letter
+    #[arg(short = 'S', long, overrides_with_all = ["ignore_case", "case_sensitive"])]
+    smart_case: bool,
+
+    /// Case sensitive search (the default; overrides -i and -S, e.g. from the config file)
+    #[arg(short = 's', long, overrides_with_all = ["ignore_case", "smart_case"])]
+    case_sensitive: bool,
+
     /// Show line numbers
     #[arg(short = 'n', long, aliases = ["l"])]
     line_numbers: bool,
 
     /// Only show count of matching lines
-    #[arg(short, long, aliases = ["c"])]
+    #[arg(short, long, aliases = ["c"], overrides_with_all = ["count_matches", "files_with_matches", "histogram", "passthru"])]
     count: bool,
 
+    /// Only show count of individual matches (several per line are counted separately)
+    #[arg(long, overrides_with_all = ["count", "files_with_matches", "histogram", "passthru"])]
+    count_matches: bool,
+
+    /// Only print the names of files containing a match
+    #[arg(short = 'l', long, overrides_with_all = ["count", "count_matches", "histogram", "passthru"])]
+    files_with_matches: bool,
+
+    /// Terminate file names with NUL instead of ':' or a newline (for `xargs -0`)
+    #[arg(short = '0', long, short_aliases = ['Z'])]
+    null: bool,
+
+    /// Treat input as NUL-separated records instead of lines, and print them NUL-terminated
+    #[arg(short = 'z', long, conflicts_with = "line_terminator")]
+    null_data: bool,
+
+    /// Treat "\r\n" as the line terminator, so a trailing '\r' is never part of the line
+    #[arg(long)]
+    crlf: bool,
+
+    /// Byte that terminates each record, e.g. ';' or '\x1e' (default: '\n')
+    #[arg(long, value_name = "BYTE", value_parser = parse_terminator)]
+    line_terminator: Option<u8>,
+
     /// Show only matching part of the line
     #[arg(short = 'o', long, aliases = ["m"])]
     only_matching: bool,
 
+    /// Like -o, but print each distinct match only the first time it is seen
+    #[arg(long, conflicts_with = "invert_match")]
+    unique: bool,
+
+    /// Instead of printing matches, print how often each distinct match occurred, most frequent first
+    #[arg(long, conflicts_with_all = ["invert_match", "unique"], overrides_with_all = ["count", "count_matches", "files_with_matches", "passthru"])]
+    histogram: bool,
+
+    /// Only match when the whole line matches the pattern
+    #[arg(short = 'x', long)]
+    line_regexp: bool,
+
     /// Invert the match (show non-matching lines)
     #[arg(short = 'v', long)]
     invert_match: bool,
 
+    /// Print every line, highlighting matches; non-matching lines pass through unchanged
+    #[arg(long, alias = "passthrough", conflicts_with_all = ["only_matching", "unique", "sorted"], overrides_with_all = ["count", "count_matches", "files_with_matches", "histogram"])]
+    passthru: bool,
+
+    /// Don't print matching lines longer than NUM bytes; show how many matches they have instead
+    #[arg(short = 'M', long, value_name = "NUM")]
+    max_columns: Option<usize>,
+
+    /// With --max-columns, show the text around each match of an overlong line instead
+    #[arg(long, requires = "max_columns")]
+    max_columns_preview: bool,
+
     /// Colorize matched text
     #[arg(long, value_name = "WHEN", default_value = "auto")]
     color: String,
+
+    /// URL template for clickable file paths and line numbers on a terminal,
+    /// e.g. "vscode://file{path}:{line}" ("none" disables hyperlinks)
+    #[arg(long, value_name = "TEMPLATE", default_value = "file://{host}{path}")]
+    hyperlink_format: String,
+
+    /// Parse each line as JSON and match the pattern only against this field, e.g. ".request.path" (repeatable)
+    #[arg(long, value_name = "PATH", value_parser = json::parse_field_path)]
+    json_field: Vec<json::FieldPath>,
+
+    /// Only consider JSON lines where a field has a value, e.g. "level=error" or ".status!=200" (repeatable)
+    #[arg(long = "where", value_name = "FIELD=VALUE", value_parser = json::parse_condition)]
+    where_: Vec<json::Condition>,
+
+    /// With --json-field/--where, print lines that are not JSON unchanged instead of skipping them
+    #[arg(long)]
+    json_passthrough: bool,
+
+    /// Parse input as CSV and search its fields; quoted fields may contain commas and newlines
+    #[arg(long, conflicts_with = "tsv")]
+    csv: bool,
+
+    /// Parse input as tab-separated values and search its fields
+    #[arg(long)]
+    tsv: bool,
+
+    /// With --csv/--tsv, only match against these columns, by header name or 1-based index (repeatable or comma-separated)
+    #[arg(long, value_name = "NAME|INDEX", value_delimiter = ',', value_parser = table::parse_column)]
+    column_select: Vec<table::ColumnSelector>,
+
+    /// With --column-select, print only the selected columns of matching records
+    #[arg(long, requires = "column_select")]
+    only_columns: bool,
+
+    /// Only consider lines whose leading timestamp is at or after TIME, e.g. "2024-05-01T12:00:00", "today" or "2h ago"
+    #[arg(long, value_name = "TIME", value_parser = timerange::parse_bound)]
+    since: Option<DateTime<Utc>>,
+
+    /// Only consider lines whose leading timestamp is at or before TIME
+    #[arg(long, value_name = "TIME", value_parser = timerange::parse_bound)]
+    until: Option<DateTime<Utc>>,
+
+    /// strftime layout of the leading timestamp, e.g. "%d/%b/%Y:%H:%M:%S %z" (default: detect ISO-8601 and syslog)
+    #[arg(long, value_name = "FORMAT")]
+    timestamp_format: Option<String>,
+
+    /// Input is sorted by time: binary-search files for --since instead of reading from the start, and stop after --until
+    #[arg(long)]
+    sorted: bool,
+
+    /// Search the stdout of COMMAND run on each file (as `COMMAND PATH`, file also on stdin), e.g. a pdftotext wrapper
+    #[arg(long, value_name = "COMMAND")]
+    pre: Option<String>,
+
+    /// With --pre, only preprocess files matching this glob, e.g. "*.pdf" (repeatable)
+    #[arg(long, value_name = "GLOB", requires = "pre", value_parser = preprocess::parse_glob)]
+    pre_glob: Vec<glob::Pattern>,
+
+    /// Approximate matching: allow up to N edits (Levenshtein distance) from the pattern
+    #[arg(long, value_name = "N")]
+    fuzzy: Option<usize>,
+
+    /// Print summary statistics after the search
+    #[arg(long)]
+    stats: bool,
+
+    /// Ignore the config file (~/.config/preg/config or $PREG_CONFIG_PATH)
+    #[arg(long)]
+    no_config: bool,
+
+    /// Browse results in a full-screen terminal UI, editing the pattern live
+    #[arg(long, conflicts_with_all = [
+        "count", "count_matches", "files_with_matches", "only_matching", "unique", "histogram", "passthru",
+        "max_columns", "json_field", "where_", "json_passthrough", "csv", "tsv", "column_select",
+        "since", "until", "sorted", "null_data", "line_terminator", "stats",
+    ])]
+    interactive: bool,
 }
 
 enum ColorWhen {
@@ -45,7 +194,18 @@ enum ColorWhen {
 }
 
 fn main() {
-    let args = CLI::parse();
+    let (argv, config) = config::merged_args();
+    let args = Cli::try_parse_from(argv).unwrap_or_else(|e| {
+        // The usage line in the error includes the config file's defaults; say where they came from
+        match config {
+            Some(path) if e.use_stderr() => {
+                let _ = e.print();
+                eprintln!("note: default arguments were read from '{}'; pass --no-config to ignore them", path.display());
+                process::exit(e.exit_code());
+            }
+            _ => e.exit(),
+        }
+    });
 
     let color_when = match args.color.as_str() {
         "always" => ColorWhen::Always,
@@ -59,88 +219,547 @@ fn main() {
         ColorWhen::Auto => atty::is(Stream::Stdout),
     };
 
-    let pattern = if args.ignore_case {
-        args.pattern.to_lowercase()
+    // Hyperlinks embed the host name and absolute paths, so they are only for terminals,
+    // even with `--color always`, and are left out when colors are turned off
+    let hyperlinks = if use_color && atty::is(Stream::Stdout) && args.hyperlink_format != "none" {
+        Some(HyperlinkFormat::new(&args.hyperlink_format))
     } else {
-        args.pattern.clone()
+        None
     };
 
-    let reader: Box<dyn BufRead> = match &args.filename {
-        Some(name) if name != "-" => {
-            match File::open(name) {
-                Ok(f) => Box::new(BufReader::new(f)),
+    let preprocessor = args.pre.as_deref().map(|command| Preprocessor::new(command, &args.pre_glob));
+    // Binary files found in directories are skipped, unless NUL separates the records or a
+    // preprocessor turns them into text
+    let skip = |path: &Path| {
+        terminator(&args) != b'\0'
+            && !preprocessor.as_ref().is_some_and(|pre| pre.applies_to(path))
+            && looks_binary(path)
+    };
+    // Inputs that cannot be read are reported and skipped; the exit code says so at the end
+    let mut failed = false;
+    let inputs = collect_inputs(&args.filename, &skip, &mut failed);
+
+    if args.interactive {
+        if let Err(e) = interactive::run(&args, &inputs, preprocessor.as_ref(), use_color) {
+            eprintln!("Error running interactive mode: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let pattern = args.pattern.as_deref().unwrap_or_default();
+    let matcher = Matcher::new(pattern, ignore_case(&args, pattern), args.fuzzy);
+    // Prefix output with the file name as soon as more than one file could be involved
+    let show_path = args.filename.len() > 1 || inputs.len() > 1
+        || args.filename.iter().any(|name| PathBuf::from(name).is_dir());
+
+    let started = Instant::now();
+    let mut stats = Stats::default();
+
+    for input in &inputs {
+        let mut first_line = 1;
+        let pre = preprocessor.as_ref().filter(|pre| input.as_ref().is_some_and(|path| pre.applies_to(path)));
+        let reader: Box<dyn BufRead> = match (input, pre) {
+            (Some(path), Some(pre)) => match pre.run(path) {
+                Ok(output) => Box::new(Cursor::new(output)),
                 Err(e) => {
-                    eprintln!("Error opening file '{}': {}", name, e);
+                    eprintln!("Error preprocessing file '{}': {}", path.display(), e);
+                    failed = true;
+                    continue;
+                }
+            },
+            (Some(path), None) => {
+                match File::open(path) {
+                    Ok(f) => match (args.sorted, args.since) {
+                        (true, Some(since)) => match skip_to(f, since, &args) {
+                            Ok((f, skipped)) => {
+                                first_line += skipped;
+                                Box::new(BufReader::new(f))
+                            }
+                            Err(e) => {
+                                eprintln!("Error seeking in file '{}': {}", path.display(), e);
+                                failed = true;
+                                continue;
+                            }
+                        },
+                        _ => Box::new(BufReader::new(f)),
+                    },
+                    Err(e) => {
+                        eprintln!("Error opening file '{}': {}", path.display(), e);
+                        failed = true;
+                        continue;
+                    }
+                }
+            }
+            (None, _) => {
+                if atty::is(Stream::Stdin) {
+                    eprintln!("No file specified and no input piped to stdin.");
                     process::exit(1);
+                } else {
+                    Box::new(BufReader::new(io::stdin()))
                 }
             }
+        };
+
+        let label = match input {
+            Some(path) => path.display().to_string(),
+            None => "(standard input)".to_string(),
+        };
+        // Hyperlinks need an absolute path to point at
+        let target = input.as_ref().and_then(|path| fs::canonicalize(path).ok());
+        let source = Source { label: &label, show_path, target: target.as_deref(), first_line };
+        if !search(reader, &source, &args, &matcher, use_color, hyperlinks.as_ref(), &mut stats) {
+            failed = true;
         }
-        _ => {
-            if atty::is(Stream::Stdin) {
-                eprintln!("No file specified and no input piped to stdin.");
-                process::exit(1);
+    }
+
+    if args.histogram {
+        stats.print_histogram();
+    }
+
+    if args.stats {
+        stats.print(started.elapsed());
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Expands the `--filename` arguments into the list of inputs to search.
+///
+/// Directories are walked recursively in sorted order; `None` stands for stdin.
+/// Directories that cannot be read are reported, skipped and flagged in `failed`.
+/// Files found in directories are left out when `skip` says so, e.g. binary files.
+fn collect_inputs(names: &[String], skip: &dyn Fn(&Path) -> bool, failed: &mut bool) -> Vec<Option<PathBuf>> {
+    if names.is_empty() {
+        return vec![None];
+    }
+
+    let mut inputs = Vec::new();
+    for name in names {
+        if name == "-" {
+            inputs.push(None);
+        } else {
+            let path = PathBuf::from(name);
+            if path.is_dir() {
+                walk(path, &mut inputs, skip, failed);
             } else {
-                Box::new(BufReader::new(io::stdin()))
+                inputs.push(Some(path));
             }
         }
+    }
+    inputs
+}
+
+/// Adds the files below `dir` to `inputs`.
+///
+/// Symbolic links to directories are not followed, so a link pointing back up
+/// the tree cannot make the walk loop.
+fn walk(dir: PathBuf, inputs: &mut Vec<Option<PathBuf>>, skip: &dyn Fn(&Path) -> bool, failed: &mut bool) {
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error reading directory '{}': {}", dir.display(), e);
+            *failed = true;
+            return;
+        }
     };
+    let mut children: Vec<(PathBuf, bool)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            // The entry's own type: a link to a directory is a link, not a directory
+            let file_type = entry.file_type().ok()?;
+            let path = entry.path();
+            match (file_type.is_dir(), file_type.is_symlink() && path.is_dir()) {
+                (_, true) => None,
+                (is_dir, false) => Some((path, is_dir)),
+            }
+        })
+        .collect();
+    children.sort();
+    for (child, is_dir) in children {
+        if is_dir {
+            walk(child, inputs, skip, failed);
+        } else if !skip(&child) {
+            inputs.push(Some(child));
+        }
+    }
+}
 
+/// Whether a file looks binary: a NUL byte in its first block, like grep decides.
+fn looks_binary(path: &Path) -> bool {
+    let mut block = Vec::with_capacity(8192);
+    File::open(path)
+        .and_then(|file| file.take(8192).read_to_end(&mut block))
+        .is_ok_and(|_| block.contains(&0))
+}
 
-    let mut matches = 0;
+/// Describes the input being searched, for labelling its results.
+struct Source<'a> {
+    /// Name printed in front of results and by `--files-with-matches`
+    label: &'a str,
+    /// Whether results are prefixed with `label` (several files are searched)
+    show_path: bool,
+    /// Absolute path used for hyperlinks; `None` for stdin
+    target: Option<&'a Path>,
+    /// Number of the first line read, past 1 when `--sorted` skipped ahead
+    first_line: usize,
+}
 
-    for (line_no, result) in reader.lines().enumerate() {
-        let Ok(line) = result else {
-            eprintln!("Error reading line {}", line_no+1);
-            continue;
-        };
+impl Source<'_> {
+    /// Prints the `path:line:` prefix of a result, as a hyperlink if enabled.
+    fn print_prefix(&self, line_no: Option<usize>, column: Option<usize>, null: bool, hyperlinks: Option<&HyperlinkFormat>) {
+        let mut prefix = String::new();
+        if self.show_path {
+            prefix.push_str(self.label);
+            prefix.push(if null { '\0' } else { ':' });
+        }
+        if let Some(line_no) = line_no {
+            prefix.push_str(&format!("{}:", line_no));
+        }
+
+        match (hyperlinks, self.target) {
+            (Some(format), Some(target)) if !prefix.is_empty() => {
+                // Keep the trailing separator outside the link
+                let (text, separator) = prefix.split_at(prefix.len() - 1);
+                let url = format.url(target, line_no, column);
+                print!("{}{}", hyperlink::link(&url, text), separator);
+            }
+            _ => print!("{}", prefix),
+        }
+    }
+}
+
+/// Searches a single input, printing results and accumulating `stats`.
+///
+/// Returns false if the input could not be searched, e.g. a `--column-select` column is missing,
+/// or reading it failed part way.
+fn search(mut reader: Box<dyn BufRead>, source: &Source, args: &Cli, matcher: &Matcher, use_color: bool, hyperlinks: Option<&HyperlinkFormat>, stats: &mut Stats) -> bool {
+    let mut matching_lines = 0;
+    let mut match_count = 0;
+    let mut bytes_scanned = 0;
+    // Cleared when reading fails part way, so the run ends with a failure
+    let mut complete_read = true;
+
+    // The text printed for a line; `long` means it is already an excerpt of an overlong line
+    let body = |line: &str, hits: &[Match], long: bool, colored: bool| {
+        if long && !args.max_columns_preview {
+            format!("[omitted long line with {} matches]", hits.len())
+        } else if colored {
+            highlight(line, hits)
+        } else {
+            line.to_string()
+        }
+    };
+
+    // Prints a line that isn't a match as is, for --passthru
+    let pass_through = |line_no: usize, line: &str, hits: &[Match], ending: &[u8], long: bool| {
+        source.print_prefix(args.line_numbers.then_some(line_no), None, args.null, hyperlinks);
+        print!("{}", body(line, hits, long, false));
+        write_ending(ending);
+    };
 
-        let haystack = if args.ignore_case { line.to_lowercase() } else { line.clone() };
-        let found = haystack.contains(&pattern);
+    // Handles one line or record; returns false once the rest of the input can be skipped
+    let mut report = |line_no: usize, line: &str, hits: &[Match], ending: &[u8], long: bool| -> bool {
+        let found = !hits.is_empty();
         let is_match = if args.invert_match { !found } else { found };
-        if is_match {
-            matches += 1;
-
-            if !args.count {
-                if args.only_matching {
-                    for (start_idx, _) in haystack.match_indices(&pattern) {
-                        let slice = &line[start_idx..start_idx+pattern.len()];
-                        if args.line_numbers {
-                            print!("{}:", line_no + 1);
+        if !is_match {
+            if args.passthru {
+                pass_through(line_no, line, hits, ending, long);
+            }
+            return true;
+        }
+        matching_lines += 1;
+        match_count += if args.invert_match { 1 } else { hits.len() };
+
+        if args.files_with_matches {
+            // One hit is enough to list the file, unless the totals are wanted
+            return args.stats;
+        }
+        if args.count || args.count_matches {
+            return true;
+        }
+
+        if args.only_matching || args.unique || args.histogram {
+            // Zero-width hits mark a JSON value that matched but couldn't be located in the raw line
+            for hit in hits.iter().filter(|hit| hit.start < hit.end) {
+                let slice = &line[hit.start..hit.end];
+                if args.unique || args.histogram {
+                    // Only the distinct strings are kept, so this stays small on huge inputs
+                    let seen = stats.match_counts.entry(slice.to_string()).or_insert(0);
+                    *seen += 1;
+                    if args.histogram || *seen > 1 {
+                        continue;
+                    }
+                }
+                // Offsets into an excerpt are not columns of the line
+                let column = (!long).then_some(hit.start + 1);
+                source.print_prefix(args.line_numbers.then_some(line_no), column, args.null, hyperlinks);
+                if matcher.is_fuzzy() {
+                    print!("~{}:", hit.distance);
+                }
+                if use_color {
+                    print!("\x1b[31m{}\x1b[0m", slice); // red
+                } else {
+                    print!("{}", slice);
+                }
+                write_ending(ending);
+            }
+        } else {
+            let column = hits.first().filter(|_| !long).map(|hit| hit.start + 1);
+            source.print_prefix(args.line_numbers.then_some(line_no), column, args.null, hyperlinks);
+            if let Some(closest) = hits.iter().map(|hit| hit.distance).min() {
+                if matcher.is_fuzzy() {
+                    print!("~{}:", closest);
+                }
+            }
+
+            print!("{}", body(line, hits, long, use_color && found && !args.invert_match));
+            write_ending(ending);
+        }
+        true
+    };
+
+    if args.csv || args.tsv {
+        let options = table::TableOptions {
+            delimiter: if args.tsv { b'\t' } else { b',' },
+            columns: &args.column_select,
+            only_columns: args.only_columns,
+            line_regexp: args.line_regexp,
+        };
+        let searched = table::search(reader, &options, matcher, &mut |line_no, text, hits, ending| {
+            shortened(text, hits, args, |text, hits, long| report(line_no, text, hits, ending, long))
+        });
+        match searched {
+            Ok(bytes) => bytes_scanned = bytes,
+            Err(e) => {
+                eprintln!("{}", e);
+                return false;
+            }
+        }
+    } else {
+        let mut buf = Vec::new();
+        let mut line_no = source.first_line - 1;
+        let terminator = terminator(args);
+        let windowed = args.since.is_some() || args.until.is_some();
+        // Lines without a timestamp (e.g. stack traces) belong to the last one seen
+        let mut current_time = None;
+        // Overlong lines are streamed unless a whole-line match or JSON parsing needs all of them
+        let streamed_columns = args.max_columns
+            .filter(|_| !args.line_regexp && args.json_field.is_empty() && args.where_.is_empty());
+        let limit = streamed_columns.map_or(usize::MAX, longline::buffer_size);
+
+        loop {
+            buf.clear();
+            let (read, complete) = match longline::read_line(&mut reader, terminator, &mut buf, limit) {
+                Ok((0, _)) => break,
+                Ok(read) => read,
+                Err(e) => {
+                    eprintln!("Error reading line {} of '{}': {}", line_no + 1, source.label, e);
+                    complete_read = false;
+                    break;
+                }
+            };
+            line_no += 1;
+            bytes_scanned += read as u64;
+
+            // A line too long to buffer is matched chunk by chunk; `buf` keeps only its start
+            let streamed = match streamed_columns {
+                Some(max_columns) if !complete => {
+                    match longline::scan(&mut reader, &mut buf, terminator, args.crlf, matcher, max_columns, args.max_columns_preview) {
+                        Ok(Some(streamed)) => {
+                            bytes_scanned += streamed.read;
+                            Some(streamed)
+                        }
+                        Ok(None) => {
+                            eprintln!("Error reading line {} of '{}': stream did not contain valid UTF-8", line_no, source.label);
+                            continue;
                         }
-                        if use_color {
-                            print!("\x1b[31m{}\x1b[0m", slice); // red
-                        } else {
-                            print!("{}", slice);
+                        Err(e) => {
+                            eprintln!("Error reading line {} of '{}': {}", line_no, source.label, e);
+                            complete_read = false;
+                            break;
                         }
-                        println!();
                     }
-                } else {
-                    if args.line_numbers {
-                        print!("{}:", line_no + 1);
+                }
+                _ => None,
+            };
+
+            // Split off the terminator so it can be written back untouched; a
+            // final line without one gets the configured terminator on output
+            let ending_len = if buf.last() != Some(&terminator) {
+                0
+            } else if args.crlf && terminator == b'\n' && buf.ends_with(b"\r\n") {
+                2
+            } else {
+                1
+            };
+            let (content, ending) = match &streamed {
+                Some(streamed) => (&buf[..], &streamed.ending[..]),
+                None => buf.split_at(buf.len() - ending_len),
+            };
+            let ending = if ending.is_empty() { std::slice::from_ref(&terminator) } else { ending };
+            let Ok(line) = std::str::from_utf8(content) else {
+                eprintln!("Error reading line {} of '{}': stream did not contain valid UTF-8", line_no, source.label);
+                continue;
+            };
+
+            // Lines outside the time window or rejected by the JSON filter are never matches
+            let mut excluded = false;
+            if windowed {
+                if let Some(time) = timerange::leading_timestamp(line, args.timestamp_format.as_deref()) {
+                    current_time = Some(time);
+                }
+                match current_time {
+                    Some(time) if args.until.is_some_and(|until| time > until) => {
+                        if args.sorted {
+                            break;
+                        }
+                        excluded = true;
                     }
+                    Some(time) => excluded = args.since.is_some_and(|since| time < since),
+                    None => excluded = true,
+                }
+            }
 
-                    if use_color && found && !args.invert_match {
-                        // highlight all pattern occurrences in line
-                        let mut last = 0;
-                        for (start, _) in haystack.match_indices(&pattern) {
-                            let orig_start = if args.ignore_case {
-                                haystack[..start].len()
-                            } else { start };
-                            let orig_end = orig_start + pattern.len();
-                            print!("{}", &line[last..orig_start]);
-                            print!("\x1b[31m{}\x1b[0m", &line[orig_start..orig_end]);
-                            last = orig_end;
+            if let (Some(streamed), false) = (&streamed, excluded) {
+                if !report(line_no, &streamed.excerpt.text, &streamed.excerpt.hits, ending, true) {
+                    break;
+                }
+                continue;
+            }
+            let hits = if excluded {
+                None
+            } else if args.json_field.is_empty() && args.where_.is_empty() {
+                Some(find_hits(matcher, line, args.line_regexp))
+            } else {
+                match json::select(line, &args.json_field, &args.where_, matcher, args.line_regexp) {
+                    json::Selection::Hits(hits) => Some(hits),
+                    json::Selection::NotJson if args.json_passthrough => {
+                        // Printed like --passthru prints lines, whether or not -v is given
+                        if !args.count && !args.count_matches && !args.files_with_matches {
+                            shortened(line, &[], args, |line, hits, long| pass_through(line_no, line, hits, ending, long));
                         }
-                        println!("{}", &line[last..]);
-                    } else {
-                        println!("{}", line);
+                        continue;
                     }
+                    json::Selection::NotJson | json::Selection::Filtered => None,
                 }
+            };
+            let Some(hits) = hits else {
+                if args.passthru {
+                    shortened(line, &[], args, |line, hits, long| pass_through(line_no, line, hits, ending, long));
+                }
+                continue;
+            };
+            if !shortened(line, &hits, args, |line, hits, long| report(line_no, line, hits, ending, long)) {
+                break;
             }
         }
     }
 
-    if args.count {
-        println!("{matches}");
+    if args.count || args.count_matches {
+        source.print_prefix(None, None, args.null, hyperlinks);
+        println!("{}", if args.count { matching_lines } else { match_count });
+    }
+
+    if args.files_with_matches && matching_lines > 0 {
+        let name = match (hyperlinks, source.target) {
+            (Some(format), Some(target)) => hyperlink::link(&format.url(target, None, None), source.label),
+            _ => source.label.to_string(),
+        };
+        if args.null {
+            print!("{}\0", name);
+        } else {
+            println!("{}", name);
+        }
+    }
+
+    stats.bytes_scanned += bytes_scanned;
+    stats.files_searched += 1;
+    if matching_lines > 0 {
+        stats.files_matched += 1;
+    }
+    stats.matching_lines += matching_lines;
+    stats.matches += match_count;
+    complete_read
+}
+
+/// Whether to match case-insensitively, from `--ignore-case` or `--smart-case`.
+fn ignore_case(args: &Cli, pattern: &str) -> bool {
+    args.ignore_case || (args.smart_case && !pattern.chars().any(char::is_uppercase))
+}
+
+/// The byte that ends each line, from `--line-terminator`/`--null-data`.
+fn terminator(args: &Cli) -> u8 {
+    match args.line_terminator {
+        Some(byte) => byte,
+        None if args.null_data => b'\0',
+        None => b'\n',
+    }
+}
+
+/// Positions a sorted log at the first line that may be at or after `since`.
+///
+/// Returns the file and the number of lines skipped (only counted when line
+/// numbers are shown, since that means reading the skipped part after all).
+fn skip_to(mut file: File, since: DateTime<Utc>, args: &Cli) -> io::Result<(File, usize)> {
+    let terminator = terminator(args);
+    let offset = timerange::seek_start(&mut file, since, args.timestamp_format.as_deref(), terminator)?;
+    let skipped = if args.line_numbers { timerange::count_lines(&mut file, offset, terminator)? } else { 0 };
+    file.seek(SeekFrom::Start(offset))?;
+    Ok((file, skipped))
+}
+
+/// Finds the hits in `line`, honouring `--line-regexp`.
+fn find_hits(matcher: &Matcher, line: &str, line_regexp: bool) -> Vec<Match> {
+    if line_regexp {
+        matcher.line_match(line).into_iter().collect()
+    } else {
+        matcher.find_iter(line)
+    }
+}
+
+/// Calls `f` with the line, or with an excerpt of it when it is longer than `--max-columns`.
+fn shortened<R>(line: &str, hits: &[Match], args: &Cli, f: impl FnOnce(&str, &[Match], bool) -> R) -> R {
+    match longline::shorten(line, hits, args.max_columns, args.max_columns_preview) {
+        Some(excerpt) => f(&excerpt.text, &excerpt.hits, true),
+        None => f(line, hits, false),
     }
-}
\ No newline at end of file
+}
+
+/// Colors every hit in `line` red.
+fn highlight(line: &str, hits: &[Match]) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0;
+    // Zero-width hits (an empty pattern, an unlocatable JSON value) have nothing to color
+    for hit in hits.iter().filter(|hit| hit.start < hit.end) {
+        out.push_str(&line[last..hit.start]);
+        out.push_str(&format!("\x1b[31m{}\x1b[0m", &line[hit.start..hit.end]));
+        last = hit.end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+/// Writes a line's original terminator, which may not be valid UTF-8 on its own.
+fn write_ending(ending: &[u8]) {
+    let _ = io::stdout().write_all(ending);
+}
+
+/// Parses `--line-terminator`: a single ASCII character or an escape such as `\0`, `\t` or `\x1e`.
+fn parse_terminator(value: &str) -> Result<u8, String> {
+    let byte = match value {
+        "\\0" => Some(b'\0'),
+        "\\n" => Some(b'\n'),
+        "\\r" => Some(b'\r'),
+        "\\t" => Some(b'\t'),
+        _ => match value.strip_prefix("\\x") {
+            Some(hex) if hex.len() == 2 => u8::from_str_radix(hex, 16).ok(),
+            Some(_) => None,
+            None if value.len() == 1 && value.is_ascii() => Some(value.as_bytes()[0]),
+            None => None,
+        },
+    };
+    byte.ok_or_else(|| format!("'{}' is not a single byte; use one ASCII character or an escape like \\0 or \\x1e", value))
+}
@@ -0,0 +1,344 @@
+/// A single hit inside a line, as byte offsets into the original line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    /// Edit distance between the matched span and the pattern (always 0 for exact matches)
+    pub distance: usize,
+}
+
+/// Decides whether (and where) a line matches the pattern.
+pub enum Matcher {
+    /// Plain substring search, the classic `contains` check
+    Exact { pattern: String, ignore_case: bool },
+    /// Substring search allowing up to `max_distance` Levenshtein edits
+    Fuzzy {
+        pattern: Vec<char>,
+        ignore_case: bool,
+        max_distance: usize,
+    },
+}
+
+impl Matcher {
+    pub fn new(pattern: &str, ignore_case: bool, fuzzy: Option<usize>) -> Self {
+        match fuzzy {
+            Some(max_distance) => Matcher::Fuzzy {
+                pattern: pattern.chars().map(|c| fold_case(c, ignore_case)).collect(),
+                ignore_case,
+                max_distance,
+            },
+            None => Matcher::Exact {
+                pattern: pattern.chars().map(|c| fold_case(c, ignore_case)).collect(),
+                ignore_case,
+            },
+        }
+    }
+
+    pub fn is_fuzzy(&self) -> bool {
+        matches!(self, Matcher::Fuzzy { .. })
+    }
+
+    /// An upper bound on the length in bytes of any hit, used to overlap chunks of long lines.
+    pub fn max_match_len(&self) -> usize {
+        match self {
+            Matcher::Exact { pattern, .. } => 4 * pattern.chars().count(),
+            Matcher::Fuzzy {
+                pattern,
+                max_distance,
+                ..
+            } => 4 * (pattern.len() + max_distance),
+        }
+    }
+
+    /// Matches `line` as a whole, as used by `--line-regexp`.
+    pub fn line_match(&self, line: &str) -> Option<Match> {
+        let distance = match self {
+            Matcher::Exact {
+                pattern,
+                ignore_case,
+            } => {
+                let matched = line
+                    .chars()
+                    .map(|c| fold_case(c, *ignore_case))
+                    .eq(pattern.chars());
+                matched.then_some(0)?
+            }
+            Matcher::Fuzzy {
+                pattern,
+                ignore_case,
+                max_distance,
+            } => {
+                let text: Vec<char> = line.chars().map(|c| fold_case(c, *ignore_case)).collect();
+                Some(levenshtein(pattern, &text)).filter(|d| d <= max_distance)?
+            }
+        };
+        Some(Match {
+            start: 0,
+            end: line.len(),
+            distance,
+        })
+    }
+
+    /// Returns all non-overlapping matches in `line`, left to right.
+    pub fn find_iter(&self, line: &str) -> Vec<Match> {
+        match self {
+            Matcher::Exact {
+                pattern,
+                ignore_case,
+            } => find_exact(line, pattern, *ignore_case),
+            Matcher::Fuzzy {
+                pattern,
+                ignore_case,
+                max_distance,
+            } => find_fuzzy(line, pattern, *ignore_case, *max_distance),
+        }
+    }
+}
+
+fn fold_case(c: char, ignore_case: bool) -> char {
+    if ignore_case {
+        c.to_lowercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// The hit of an empty pattern: it matches every line, like `str::contains("")`.
+const EMPTY_HIT: Match = Match {
+    start: 0,
+    end: 0,
+    distance: 0,
+};
+
+fn find_exact(line: &str, pattern: &str, ignore_case: bool) -> Vec<Match> {
+    if pattern.is_empty() {
+        return vec![EMPTY_HIT];
+    }
+    if !ignore_case {
+        return line
+            .match_indices(pattern)
+            .map(|(start, _)| Match {
+                start,
+                end: start + pattern.len(),
+                distance: 0,
+            })
+            .collect();
+    }
+
+    // Folding can change a char's length in bytes, so search the folded line
+    // and map every byte of it back to the start of its char in the original
+    let mut haystack = String::with_capacity(line.len());
+    let mut offsets = Vec::with_capacity(line.len() + 1);
+    for (i, c) in line.char_indices() {
+        haystack.push(fold_case(c, true));
+        offsets.resize(haystack.len(), i);
+    }
+    offsets.push(line.len());
+
+    haystack
+        .match_indices(pattern)
+        .map(|(start, _)| Match {
+            start: offsets[start],
+            end: offsets[start + pattern.len()],
+            distance: 0,
+        })
+        .collect()
+}
+
+/// Approximate substring search (Sellers' algorithm).
+///
+/// Runs the Levenshtein dynamic program with a free starting position in the
+/// text, tracking where each alignment began so the matched span can be
+/// recovered. Overlapping spans within budget are collapsed into one hit: the
+/// one with the smallest distance, preferring spans whose length is closest
+/// to the pattern's on ties.
+fn find_fuzzy(line: &str, pattern: &[char], ignore_case: bool, max_distance: usize) -> Vec<Match> {
+    if pattern.is_empty() {
+        return vec![EMPTY_HIT];
+    }
+
+    // Byte offset of every char boundary, including the end of the line
+    let mut offsets: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+    offsets.push(line.len());
+    let text: Vec<char> = line.chars().map(|c| fold_case(c, ignore_case)).collect();
+
+    let m = pattern.len();
+    // cost[i] / origin[i]: best distance aligning pattern[..i] ending at the
+    // current text position, and the text position that alignment started at
+    let mut cost: Vec<usize> = (0..=m).collect();
+    let mut origin: Vec<usize> = vec![0; m + 1];
+
+    // Every end position within budget, with the start of its best alignment
+    let mut candidates = Vec::new();
+
+    for j in 1..=text.len() {
+        let mut diag_cost = cost[0];
+        let mut diag_origin = origin[0];
+        cost[0] = 0;
+        origin[0] = j;
+
+        for i in 1..=m {
+            let substitution = diag_cost + (pattern[i - 1] != text[j - 1]) as usize;
+            let deletion = cost[i - 1] + 1;
+            let insertion = cost[i] + 1;
+
+            let (next_cost, next_origin) = if substitution <= deletion && substitution <= insertion
+            {
+                (substitution, diag_origin)
+            } else if deletion <= insertion {
+                (deletion, origin[i - 1])
+            } else {
+                (insertion, origin[i])
+            };
+
+            diag_cost = cost[i];
+            diag_origin = origin[i];
+            cost[i] = next_cost;
+            origin[i] = next_origin;
+        }
+
+        if cost[m] <= max_distance && origin[m] < j {
+            candidates.push(Match {
+                start: origin[m],
+                end: j,
+                distance: cost[m],
+            });
+        }
+    }
+
+    // Overlapping candidates describe the same hit; keep the best of each cluster
+    let rank = |h: &Match| (h.distance, (h.end - h.start).abs_diff(m));
+    let mut hits: Vec<Match> = Vec::new();
+    let mut best: Option<Match> = None;
+    for candidate in candidates {
+        if hits.last().is_some_and(|h| candidate.start < h.end) {
+            continue;
+        }
+        best = match best {
+            Some(b) if candidate.start < b.end => Some(if rank(&candidate) < rank(&b) {
+                candidate
+            } else {
+                b
+            }),
+            Some(b) => {
+                hits.push(b);
+                Some(candidate)
+            }
+            None => Some(candidate),
+        };
+    }
+    hits.extend(best);
+
+    hits.into_iter()
+        .map(|h| Match {
+            start: offsets[h.start],
+            end: offsets[h.end],
+            ..h
+        })
+        .collect()
+}
+
+/// Plain edit distance between two whole strings.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diag + (ca != cb) as usize)
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(matcher: &Matcher, line: &str) -> Vec<(usize, usize, usize)> {
+        matcher
+            .find_iter(line)
+            .into_iter()
+            .map(|m| (m.start, m.end, m.distance))
+            .collect()
+    }
+
+    #[test]
+    fn ignore_case_offsets_point_into_the_original_line() {
+        // 'İ' is two bytes but folds to the one-byte 'i', shifting everything after it
+        let matcher = Matcher::new("x", true, None);
+        assert_eq!(spans(&matcher, "İx"), vec![(2, 3, 0)]);
+
+        let matcher = Matcher::new("STAN", true, None);
+        let line = "İstanbul";
+        let hits = spans(&matcher, line);
+        assert_eq!(hits, vec![(2, 6, 0)]);
+        assert_eq!(&line[2..6], "stan");
+    }
+
+    #[test]
+    fn fuzzy_finds_exact_occurrences_at_distance_zero() {
+        let matcher = Matcher::new("hello", false, Some(1));
+        assert_eq!(spans(&matcher, "say hello there"), vec![(4, 9, 0)]);
+    }
+
+    #[test]
+    fn fuzzy_allows_each_kind_of_edit() {
+        let matcher = Matcher::new("hello", false, Some(1));
+        // Substitution, deletion and insertion
+        assert_eq!(spans(&matcher, "a hallo b"), vec![(2, 7, 1)]);
+        assert_eq!(spans(&matcher, "a helo b"), vec![(2, 6, 1)]);
+        assert_eq!(spans(&matcher, "a hexllo b"), vec![(2, 8, 1)]);
+    }
+
+    #[test]
+    fn fuzzy_respects_the_budget() {
+        let matcher = Matcher::new("hello", false, Some(1));
+        assert_eq!(spans(&matcher, "a hxllx b"), vec![]);
+
+        let matcher = Matcher::new("hello", false, Some(2));
+        assert_eq!(spans(&matcher, "a hxllx b"), vec![(2, 7, 2)]);
+    }
+
+    #[test]
+    fn fuzzy_collapses_overlapping_candidates_into_one_hit() {
+        // "hell", "hello" and "hellox" all end within budget; only the exact one is kept
+        let matcher = Matcher::new("hello", false, Some(1));
+        assert_eq!(spans(&matcher, "hellox"), vec![(0, 5, 0)]);
+    }
+
+    #[test]
+    fn fuzzy_keeps_separate_hits_apart() {
+        let matcher = Matcher::new("abc", false, Some(1));
+        assert_eq!(
+            spans(&matcher, "abc xbc abd"),
+            vec![(0, 3, 0), (4, 7, 1), (8, 11, 1)]
+        );
+    }
+
+    #[test]
+    fn fuzzy_folds_case_and_reports_byte_offsets() {
+        let matcher = Matcher::new("ÉTÉ", true, Some(0));
+        let line = "un été chaud";
+        let hits = spans(&matcher, line);
+        assert_eq!(hits, vec![(3, 8, 0)]);
+        assert_eq!(&line[3..8], "été");
+    }
+
+    #[test]
+    fn empty_pattern_matches_every_line() {
+        assert_eq!(
+            spans(&Matcher::new("", false, None), "abc"),
+            vec![(0, 0, 0)]
+        );
+        assert_eq!(spans(&Matcher::new("", true, None), ""), vec![(0, 0, 0)]);
+        assert_eq!(
+            spans(&Matcher::new("", false, Some(1)), "abc"),
+            vec![(0, 0, 0)]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    time::UNIX_EPOCH,
+};
+
+/// Runs `--pre` converters and caches their output by path and modification time.
+pub struct Preprocessor {
+    command: String,
+    globs: Vec<glob::Pattern>,
+    cache_dir: Option<PathBuf>,
+}
+
+/// Parses a `--pre-glob` pattern such as `*.pdf`.
+pub fn parse_glob(value: &str) -> Result<glob::Pattern, String> {
+    glob::Pattern::new(value).map_err(|e| format!("invalid glob '{}': {}", value, e))
+}
+
+impl Preprocessor {
+    pub fn new(command: &str, globs: &[glob::Pattern]) -> Self {
+        Preprocessor {
+            command: command.to_string(),
+            globs: globs.to_vec(),
+            cache_dir: cache_dir(),
+        }
+    }
+
+    /// Whether `path` goes through the converter: every file when no globs were
+    /// given, otherwise those whose name (or, for globs containing `/`, whose
+    /// path) matches one of them.
+    pub fn applies_to(&self, path: &Path) -> bool {
+        if self.globs.is_empty() {
+            return true;
+        }
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+        self.globs.iter().any(|glob| {
+            if glob.as_str().contains('/') {
+                glob.matches_path(path)
+            } else {
+                glob.matches_path(name)
+            }
+        })
+    }
+
+    /// Returns the converter's stdout for `path`, from the cache when the file is unchanged.
+    ///
+    /// The converter is run as `COMMAND PATH` with the file also on its stdin,
+    /// so both `pdftotext`-style wrappers and filters work.
+    pub fn run(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let cached = self.cache_path(path);
+        if let Some(output) = cached.as_ref().and_then(|cached| fs::read(cached).ok()) {
+            return Ok(output);
+        }
+
+        let mut parts = self.command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--pre command is empty"))?;
+        let output = Command::new(program)
+            .args(parts)
+            .arg(path)
+            .stdin(fs::File::open(path)?)
+            .stderr(Stdio::piped())
+            .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = format!("'{}' failed ({})", self.command, output.status);
+            if !stderr.trim().is_empty() {
+                message.push_str(&format!(": {}", stderr.trim()));
+            }
+            return Err(io::Error::other(message));
+        }
+
+        // A failed cache write only costs a re-run next time
+        if let Some(cached) = cached {
+            let _ = store(&cached, &output.stdout);
+        }
+        Ok(output.stdout)
+    }
+
+    /// Cache entry for `path`, keyed by the command, absolute path, size and mtime.
+    fn cache_path(&self, path: &Path) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        let mut hasher = DefaultHasher::new();
+        self.command.hash(&mut hasher);
+        fs::canonicalize(path).ok()?.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        modified.hash(&mut hasher);
+        Some(dir.join(format!("{:016x}", hasher.finish())))
+    }
+}
+
+/// Writes a cache entry so that it appears whole or not at all.
+///
+/// The entry's key never changes, so a partly written file (from a killed
+/// process, a full disk or another preg writing at the same time) would be
+/// served forever. The output goes to a temporary file next to the entry
+/// instead, which is renamed into place once complete.
+fn store(cached: &Path, output: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(cached.parent().unwrap_or(Path::new(".")))?;
+    let temporary = cached.with_extension(format!("{}.tmp", process::id()));
+    let written = fs::write(&temporary, output).and_then(|()| fs::rename(&temporary, cached));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+/// `$XDG_CACHE_HOME/preg/pre`, falling back to `~/.cache/preg/pre`.
+fn cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("preg").join("pre"))
+}
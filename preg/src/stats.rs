@@ -0,0 +1,34 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Running totals collected while searching, printed by `--stats`.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub matching_lines: usize,
+    pub matches: usize,
+    pub bytes_scanned: u64,
+    /// How often each distinct matched string was seen, for `--unique` and `--histogram`
+    pub match_counts: HashMap<String, u64>,
+}
+
+impl Stats {
+    pub fn print(&self, elapsed: Duration) {
+        println!();
+        println!("{} files searched", self.files_searched);
+        println!("{} files matched", self.files_matched);
+        println!("{} matching lines", self.matching_lines);
+        println!("{} matches", self.matches);
+        println!("{} bytes scanned", self.bytes_scanned);
+        println!("{:.6} seconds elapsed", elapsed.as_secs_f64());
+    }
+
+    /// Prints `match_counts` like `sort | uniq -c | sort -rn`: most frequent first, ties alphabetically.
+    pub fn print_histogram(&self) {
+        let mut counts: Vec<(&String, &u64)> = self.match_counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (text, count) in counts {
+            println!("{:>7} {}", count, text);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::BufRead;
+
+use crate::find_hits;
+use crate::matcher::{Match, Matcher};
+
+/// Picks a column by 1-based position or by its header name.
+#[derive(Debug, Clone)]
+pub enum ColumnSelector {
+    Index(usize),
+    Name(String),
+}
+
+/// Parses a `--column-select` entry: a number is a 1-based index, anything else a header name.
+pub fn parse_column(value: &str) -> Result<ColumnSelector, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("column indexes start at 1".to_string()),
+        Ok(index) => Ok(ColumnSelector::Index(index - 1)),
+        Err(_) => Ok(ColumnSelector::Name(value.to_string())),
+    }
+}
+
+/// Receives each record as `(line, text, hits, ending)`; returns false to stop early.
+pub type Report<'r> = dyn FnMut(usize, &str, &[Match], &[u8]) -> bool + 'r;
+
+/// Options for `--csv`/`--tsv` searches.
+pub struct TableOptions<'a> {
+    pub delimiter: u8,
+    pub columns: &'a [ColumnSelector],
+    /// Print only the selected columns instead of the whole record
+    pub only_columns: bool,
+    pub line_regexp: bool,
+}
+
+/// Searches delimited records, matching the pattern only against the selected columns.
+///
+/// The first record is the header and is not searched. Quoted fields may
+/// contain delimiters and newlines; a record is reported at the line it starts
+/// on. Records are re-encoded for output, so `report` receives the printable
+/// record text with hits mapped into it.
+///
+/// # Returns
+/// * `Ok(u64)` - The number of bytes read
+/// * `Err(String)` - Why the header could not be read or a column was not found
+pub fn search(
+    reader: Box<dyn BufRead>,
+    options: &TableOptions,
+    matcher: &Matcher,
+    report: &mut Report,
+) -> Result<u64, String> {
+    let mut records = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers = records
+        .headers()
+        .map_err(|e| format!("Error reading header: {}", e))?;
+    let selected = resolve(options.columns, headers)
+        .map_err(|name| format!("Column '{}' not found in header", name))?;
+
+    let mut record = csv::StringRecord::new();
+    loop {
+        match records.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(e) => {
+                eprintln!("Error reading record: {}", e);
+                if e.is_io_error() {
+                    break;
+                }
+                continue;
+            }
+        }
+        let line_no = record
+            .position()
+            .map_or(0, |position| position.line() as usize);
+
+        let mut text = String::new();
+        let mut hits = Vec::new();
+        let mut first = true;
+        for (index, field) in record.iter().enumerate() {
+            let is_selected = selected
+                .as_ref()
+                .is_none_or(|columns| columns.contains(&index));
+            if options.only_columns && !is_selected {
+                continue;
+            }
+            if !first {
+                text.push(options.delimiter as char);
+            }
+            first = false;
+
+            let field_hits = if is_selected {
+                find_hits(matcher, field, options.line_regexp)
+            } else {
+                Vec::new()
+            };
+            let (encoded, mapped) = encode(field, options.delimiter, &field_hits);
+            hits.extend(mapped.into_iter().map(|hit| Match {
+                start: text.len() + hit.start,
+                end: text.len() + hit.end,
+                ..hit
+            }));
+            text.push_str(&encoded);
+        }
+
+        if !report(line_no, &text, &hits, b"\n") {
+            break;
+        }
+    }
+
+    Ok(records.position().byte())
+}
+
+/// Turns the selectors into field indexes; `None` means every column is searched.
+fn resolve(
+    columns: &[ColumnSelector],
+    headers: &csv::StringRecord,
+) -> Result<Option<Vec<usize>>, String> {
+    if columns.is_empty() {
+        return Ok(None);
+    }
+    columns
+        .iter()
+        .map(|column| match column {
+            ColumnSelector::Index(index) if *index < headers.len() => Ok(*index),
+            ColumnSelector::Index(index) => Err((index + 1).to_string()),
+            ColumnSelector::Name(name) => headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| name.clone()),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Quotes a field the way the csv writer would and maps its hits into the encoded text.
+///
+/// Inside a field with doubled quotes the offsets no longer line up, so the
+/// whole field is highlighted instead.
+fn encode(field: &str, delimiter: u8, hits: &[Match]) -> (String, Vec<Match>) {
+    let needs_quotes = field
+        .bytes()
+        .any(|byte| matches!(byte, b'"' | b'\n' | b'\r') || byte == delimiter);
+    if !needs_quotes {
+        return (field.to_string(), hits.to_vec());
+    }
+
+    let encoded = format!("\"{}\"", field.replace('"', "\"\""));
+    let mapped = if field.contains('"') {
+        match hits.iter().map(|hit| hit.distance).min() {
+            Some(distance) => vec![Match {
+                start: 0,
+                end: encoded.len(),
+                distance,
+            }],
+            None => Vec::new(),
+        }
+    } else {
+        hits.iter()
+            .map(|hit| Match {
+                start: hit.start + 1,
+                end: hit.end + 1,
+                ..*hit
+            })
+            .collect()
+    };
+    (encoded, mapped)
+}
@@ -0,0 +1,296 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+};
+
+/// ISO-8601 layouts tried by auto-detection, with and without an offset.
+const ISO_WITH_OFFSET: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f%#z", "%Y-%m-%d %H:%M:%S%.f%#z"];
+const ISO_LOCAL: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+/// Classic syslog layout, e.g. `Oct  9 02:17:01`, which has no year.
+const SYSLOG: &str = "%Y %b %e %H:%M:%S";
+
+/// Parses `--since`/`--until`.
+///
+/// Accepts relative times (`15m`, `2h ago`, `3d`, `1w`), `now`, `today`,
+/// `yesterday`, a date (`2024-05-01`) or any timestamp auto-detection knows.
+pub fn parse_bound(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    let now = Local::now();
+    let midnight = |date: NaiveDate| local_to_utc(date.and_hms_opt(0, 0, 0).unwrap_or_default());
+
+    match value {
+        "now" => return Ok(now.with_timezone(&Utc)),
+        "today" => return Ok(midnight(now.date_naive())),
+        "yesterday" => return Ok(midnight(now.date_naive() - Duration::days(1))),
+        _ => {}
+    }
+
+    let relative = value.strip_suffix("ago").unwrap_or(value).trim_end();
+    if let Some(unit) = relative.chars().last() {
+        let amount = relative[..relative.len() - unit.len_utf8()].trim();
+        if let Ok(amount) = amount.parse::<i64>() {
+            let span = match unit {
+                's' => Duration::seconds(amount),
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                'w' => Duration::weeks(amount),
+                _ => {
+                    return Err(format!(
+                        "unknown time unit '{}' in '{}' (use s, m, h, d or w)",
+                        unit, value
+                    ))
+                }
+            };
+            return Ok(now.with_timezone(&Utc) - span);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(midnight(date));
+    }
+    match leading_timestamp(value, None) {
+        Some(time) => Ok(time),
+        None => Err(format!(
+            "'{}' is not a time; try '2024-05-01T12:00:00', '2024-05-01' or '2h ago'",
+            value
+        )),
+    }
+}
+
+/// Reads the timestamp at the start of `line`.
+///
+/// With a strftime `format` only that layout is tried; otherwise ISO-8601 and
+/// syslog timestamps are detected. A leading `[` is skipped either way.
+/// Timestamps without an offset are taken to be local time.
+pub fn leading_timestamp(line: &str, format: Option<&str>) -> Option<DateTime<Utc>> {
+    let line = line.trim_start().trim_start_matches('[');
+
+    if let Some(format) = format {
+        return DateTime::parse_and_remainder(line, format)
+            .map(|(time, _)| time.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                NaiveDateTime::parse_and_remainder(line, format)
+                    .ok()
+                    .map(|(time, _)| local_to_utc(time))
+            });
+    }
+
+    if !line.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    for format in ISO_WITH_OFFSET {
+        if let Ok((time, _)) = DateTime::parse_and_remainder(line, format) {
+            return Some(time.with_timezone(&Utc));
+        }
+    }
+    for format in ISO_LOCAL {
+        if let Ok((time, _)) = NaiveDateTime::parse_and_remainder(line, format) {
+            return Some(local_to_utc(time));
+        }
+    }
+
+    // Syslog omits the year: assume the current one, unless that lands in the future
+    let now = Local::now();
+    let (time, _) =
+        NaiveDateTime::parse_and_remainder(&format!("{} {}", now.year(), line), SYSLOG).ok()?;
+    let time = local_to_utc(time);
+    if time > now.with_timezone(&Utc) + Duration::days(1) {
+        time.with_year(now.year() - 1)
+    } else {
+        Some(time)
+    }
+}
+
+fn local_to_utc(time: NaiveDateTime) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map_or_else(|| time.and_utc(), |local| local.with_timezone(&Utc))
+}
+
+/// Binary-searches a log sorted by time for a line-start offset to begin reading at.
+///
+/// The result never skips a line at or after `since`: every line before the
+/// returned offset is known to carry (or continue) an earlier timestamp. Lines
+/// are still filtered one by one afterwards, so landing a little early is fine.
+pub fn seek_start(
+    file: &mut File,
+    since: DateTime<Utc>,
+    format: Option<&str>,
+    terminator: u8,
+) -> io::Result<u64> {
+    let len = file.metadata()?.len();
+    let mut lo = 0;
+    let mut hi = len;
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match first_timestamp_after(file, mid, format, terminator)? {
+            Some((time, next_line)) if time < since => lo = next_line,
+            _ => hi = mid,
+        }
+    }
+    Ok(lo.min(len))
+}
+
+/// Finds the first timestamped line starting at or after `offset`.
+///
+/// Returns its timestamp and the offset of the line that follows it.
+fn first_timestamp_after(
+    file: &mut File,
+    offset: u64,
+    format: Option<&str>,
+    terminator: u8,
+) -> io::Result<Option<(DateTime<Utc>, u64)>> {
+    let mut position = offset.saturating_sub(1);
+    file.seek(SeekFrom::Start(position))?;
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+
+    // Unless we are at the very start, finish the line `offset - 1` is part of
+    if offset > 0 {
+        position += reader.read_until(terminator, &mut buf)? as u64;
+    }
+    loop {
+        buf.clear();
+        let read = reader.read_until(terminator, &mut buf)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        position += read as u64;
+        if let Some(time) = leading_timestamp(&String::from_utf8_lossy(&buf), format) {
+            return Ok(Some((time, position)));
+        }
+    }
+}
+
+/// Counts the lines before `offset`, so line numbers stay right after seeking.
+pub fn count_lines(file: &mut File, offset: u64, terminator: u8) -> io::Result<usize> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut remaining = file.by_ref().take(offset);
+    let mut chunk = [0u8; 64 * 1024];
+    let mut lines = 0;
+    loop {
+        let read = remaining.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(lines);
+        }
+        lines += chunk[..read]
+            .iter()
+            .filter(|&&byte| byte == terminator)
+            .count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A log with one timestamped line per hour from 10:00, each followed by a continuation line.
+    fn log() -> File {
+        let mut file = tempfile::tempfile().expect("failed to create temporary file");
+        for hour in 10..15 {
+            writeln!(file, "2024-05-01T{hour}:00:00Z event {hour}").unwrap();
+            writeln!(file, "  continuation of {hour}").unwrap();
+        }
+        file
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, hour, minute, 0).unwrap()
+    }
+
+    /// The line `seek_start` lands on, and its line number.
+    fn landing(file: &mut File, since: DateTime<Utc>) -> (String, usize) {
+        let offset = seek_start(file, since, None, b'\n').unwrap();
+        let number = count_lines(file, offset, b'\n').unwrap() + 1;
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line).unwrap();
+        (line.trim_end().to_string(), number)
+    }
+
+    #[test]
+    fn target_before_the_first_line_starts_at_the_top() {
+        let mut file = log();
+        assert_eq!(seek_start(&mut file, at(9, 0), None, b'\n').unwrap(), 0);
+        assert_eq!(
+            landing(&mut file, at(10, 0)),
+            ("2024-05-01T10:00:00Z event 10".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn target_after_the_last_line_starts_after_the_last_timestamp() {
+        let mut file = log();
+        // Only the continuation of the last line is left to read
+        assert_eq!(
+            landing(&mut file, at(20, 0)),
+            ("  continuation of 14".to_string(), 10)
+        );
+    }
+
+    #[test]
+    fn target_between_lines_lands_after_the_last_earlier_timestamp() {
+        let mut file = log();
+        // Right after the 11:00 line: its continuation is read again, 12:00 is not skipped
+        assert_eq!(
+            landing(&mut file, at(12, 0)),
+            ("  continuation of 11".to_string(), 4)
+        );
+        assert_eq!(
+            landing(&mut file, at(12, 30)),
+            ("  continuation of 12".to_string(), 6)
+        );
+    }
+
+    #[test]
+    fn continuation_lines_are_not_timestamps() {
+        let mut file = log();
+        // Starting inside the first continuation line skips to the 11:00 line
+        let first_line = "2024-05-01T10:00:00Z event 10\n".len() as u64;
+        let (time, next) = first_timestamp_after(&mut file, first_line + 3, None, b'\n')
+            .unwrap()
+            .unwrap();
+        assert_eq!(time, at(11, 0));
+        assert_eq!(count_lines(&mut file, next, b'\n').unwrap(), 3);
+    }
+
+    #[test]
+    fn count_lines_counts_terminators_before_the_offset() {
+        let mut file = log();
+        assert_eq!(count_lines(&mut file, 0, b'\n').unwrap(), 0);
+        let len = file.metadata().unwrap().len();
+        assert_eq!(count_lines(&mut file, len, b'\n').unwrap(), 10);
+        assert_eq!(count_lines(&mut file, len, b';').unwrap(), 0);
+    }
+
+    #[test]
+    fn relative_bounds() {
+        let hours = |value: &str| {
+            let bound = parse_bound(value).unwrap();
+            (Utc::now() - bound).num_minutes() as f64 / 60.0
+        };
+        assert!((hours("2h ago") - 2.0).abs() < 0.1);
+        assert!((hours("2h") - 2.0).abs() < 0.1);
+        assert!((hours("90m") - 1.5).abs() < 0.1);
+        assert!((hours("1w") - 168.0).abs() < 0.1);
+
+        assert!(parse_bound("3 months").is_err());
+        assert!(parse_bound("3y").is_err());
+        assert!(parse_bound("soon").is_err());
+    }
+
+    #[test]
+    fn absolute_bounds() {
+        assert_eq!(parse_bound("2024-05-01T12:30:00Z").unwrap(), at(12, 30));
+        assert_eq!(
+            parse_bound("2024-05-01T14:30:00+02:00").unwrap(),
+            at(12, 30)
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Golden-file tests: run preg against the fixture tree and compare its
+//! stdout, stderr and exit code with the snapshots in `tests/golden`.
+//!
+//! After an intended change in output, run `PREG_BLESS=1 cargo test` to
+//! rewrite the snapshots, then review the diff.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::{env, fs, path::PathBuf};
+
+/// The flags whose every combination is snapshotted, with the name used in the file name.
+const FLAGS: [(&str, &str); 5] = [
+    ("-i", "i"),
+    ("-o", "o"),
+    ("-v", "v"),
+    ("-n", "n"),
+    ("-c", "c"),
+];
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// preg run from `tests/fixtures`, isolated from the user's config file.
+fn preg() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_preg"));
+    command
+        .current_dir(manifest_dir().join("tests/fixtures"))
+        .env("PREG_CONFIG_PATH", "");
+    command
+}
+
+/// Runs preg and renders everything observable about the run as one text.
+fn snapshot(args: &[&str]) -> String {
+    let output = preg().args(args).output().expect("failed to run preg");
+    format!(
+        "args: {}\nstatus: {}\n--- stdout\n{}--- stderr\n{}",
+        args.join(" "),
+        output
+            .status
+            .code()
+            .map_or("signal".to_string(), |code| code.to_string()),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+/// Compares each case with its golden file, or rewrites the files when blessing.
+fn check(cases: &[(String, Vec<&str>)]) {
+    let golden = manifest_dir().join("tests/golden");
+    let bless = env::var_os("PREG_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for (name, args) in cases {
+        let path = golden.join(format!("{}.txt", name));
+        let actual = snapshot(args);
+        if bless {
+            fs::write(&path, &actual).expect("failed to write golden file");
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}:\n--- expected\n{}\n--- actual\n{}",
+                path.display(),
+                expected,
+                actual
+            )),
+            Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} snapshot(s) differ (rerun with PREG_BLESS=1 if the change is intended):\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn every_flag_combination() {
+    let mut cases = Vec::new();
+    for color in ["never", "always"] {
+        for set in 0..1 << FLAGS.len() {
+            let chosen: Vec<_> = FLAGS
+                .iter()
+                .enumerate()
+                .filter(|(bit, _)| set & (1 << bit) != 0)
+                .map(|(_, flag)| flag)
+                .collect();
+
+            let mut name = String::from("the");
+            for (_, short) in &chosen {
+                name.push('-');
+                name.push_str(short);
+            }
+            name.push_str("-color-");
+            name.push_str(color);
+
+            let mut args = vec!["-p", "the", "-f", "tree"];
+            args.extend(chosen.iter().map(|(flag, _)| *flag));
+            args.extend(["--color", color]);
+            cases.push((name, args));
+        }
+    }
+    check(&cases);
+}
+
+#[test]
+fn errors() {
+    check(&[
+        (
+            "missing-file".to_string(),
+            vec!["-p", "the", "-f", "missing.txt"],
+        ),
+        (
+            "conflicting-modes".to_string(),
+            vec!["-p", "the", "-f", "tree", "--passthru", "-o"],
+        ),
+        ("missing-pattern".to_string(), vec!["-f", "tree"]),
+    ]);
+}
+
+#[test]
+fn single_file_has_no_path_prefix() {
+    preg()
+        .args(["-p", "river", "-f", "tree/poem.txt", "-n"])
+        .assert()
+        .success()
+        .stdout("2:and the river ran on.\n");
+}
+
+#[test]
+fn reads_stdin() {
+    preg()
+        .args(["-p", "b", "-f", "-"])
+        .write_stdin("a\nb\nabc\n")
+        .assert()
+        .success()
+        .stdout("b\nabc\n");
+}
+
+#[test]
+fn no_match_is_not_an_error() {
+    preg()
+        .args(["-p", "zebra", "-f", "tree"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn color_always_highlights_matches() {
+    preg()
+        .args(["-p", "river", "-f", "tree/poem.txt", "--color", "always"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[31mriver\x1b[0m"));
+}
+
+#[test]
+fn no_hyperlinks_when_piped() {
+    preg()
+        .args(["-p", "river", "-f", "tree", "-n", "--color", "always"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b]8;").not());
+}
+
+#[test]
+fn unreadable_file_does_not_stop_the_search() {
+    preg()
+        .args([
+            "-p",
+            "the",
+            "-f",
+            "tree/poem.txt",
+            "missing.txt",
+            "tree/notes/todo.md",
+            "-c",
+        ])
+        .assert()
+        .code(1)
+        .stdout(
+            predicate::str::contains("tree/poem.txt:")
+                .and(predicate::str::contains("tree/notes/todo.md:")),
+        )
+        .stderr(predicate::str::contains("Error opening file 'missing.txt'"));
+}
+
+#[test]
+fn json_field_highlights_the_selected_field() {
+    preg()
+        .args([
+            "-p",
+            "x",
+            "-f",
+            "-",
+            "--json-field",
+            ".b",
+            "--color",
+            "always",
+        ])
+        .write_stdin("{\"a\":\"x\",\"b\":\"x\"}\n")
+        .assert()
+        .success()
+        .stdout("{\"a\":\"x\",\"b\":\"\x1b[31mx\x1b[0m\"}\n");
+}
+
+#[test]
+fn column_index_past_the_last_column_is_not_found() {
+    preg()
+        .args(["-p", "2", "-f", "-", "--csv", "--column-select", "5"])
+        .write_stdin("a,b\n1,2\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr("Column '5' not found in header\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn directory_links_are_not_followed() {
+    let dir = tempfile::tempdir().expect("failed to create temporary directory");
+    fs::write(dir.path().join("a.txt"), "foo\n").expect("failed to write file");
+    std::os::unix::fs::symlink(".", dir.path().join("self")).expect("failed to create link");
+
+    preg()
+        .current_dir(dir.path())
+        .args(["-p", "foo", "-f", ".", "-c"])
+        .assert()
+        .success()
+        .stdout("./a.txt:1\n")
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn binary_files_are_skipped_in_directories() {
+    let dir = tempfile::tempdir().expect("failed to create temporary directory");
+    fs::write(dir.path().join("a.txt"), "foo\n").expect("failed to write file");
+    fs::write(dir.path().join("blob"), b"foo\0\xff\xfe\n").expect("failed to write file");
+
+    preg()
+        .current_dir(dir.path())
+        .args(["-p", "foo", "-f", ".", "-c"])
+        .assert()
+        .success()
+        .stdout("./a.txt:1\n")
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn read_errors_name_the_file() {
+    preg()
+        .args(["-p", "foo", "-f", "-"])
+        .write_stdin(&b"foo\n\xff\n"[..])
+        .assert()
+        .stdout("foo\n")
+        .stderr(predicate::str::contains("line 2 of '(standard input)'"));
+}
+
+#[test]
+fn interactive_rejects_options_it_cannot_honor() {
+    preg()
+        .args(["--interactive", "-p", "the", "-f", "tree", "--csv"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with '--csv'"));
+}
+
+#[test]
+fn json_passthrough_lines_keep_their_prefix() {
+    preg()
+        .args([
+            "-p",
+            "x",
+            "-f",
+            "-",
+            "--json-field",
+            ".a",
+            "--json-passthrough",
+            "-n",
+        ])
+        .write_stdin("{\"a\":\"x\"}\nplain x\n{\"a\":\"y\"}\n")
+        .assert()
+        .success()
+        .stdout("1:{\"a\":\"x\"}\n2:plain x\n");
+}
+
+/// preg with the given config file contents as its defaults.
+fn preg_with_config(config: &str) -> (Command, tempfile::NamedTempFile) {
+    let file = tempfile::NamedTempFile::new().expect("failed to create config file");
+    fs::write(file.path(), config).expect("failed to write config file");
+    let mut command = preg();
+    command.env("PREG_CONFIG_PATH", file.path());
+    (command, file)
+}
+
+#[test]
+fn command_line_output_mode_overrides_the_config() {
+    let (mut command, _config) = preg_with_config("--count\n");
+    command
+        .args(["-p", "river", "-f", "tree", "-l"])
+        .assert()
+        .success()
+        .stdout("tree/poem.txt\n");
+}
+
+#[test]
+fn case_sensitive_overrides_the_config() {
+    let (mut command, _config) = preg_with_config("--ignore-case\n");
+    command
+        .args(["-p", "end", "-f", "tree/poem.txt", "-s"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn config_conflicts_name_the_config_file() {
+    let (mut command, config) = preg_with_config("--count\n");
+    command
+        .args(["-p", "river", "-f", "tree", "--interactive"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(format!(
+            "default arguments were read from '{}'",
+            config.path().display()
+        )));
+}
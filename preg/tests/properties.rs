@@ -0,0 +1,89 @@
+//! Property tests: invariants between flags that must hold for any input.
+
+use assert_cmd::Command;
+use proptest::prelude::*;
+use std::{collections::BTreeSet, io::Write};
+use tempfile::NamedTempFile;
+
+/// Runs preg on `lines` (written to a temporary file) and returns its stdout.
+fn run(lines: &[String], args: &[&str]) -> String {
+    let mut file = NamedTempFile::new().expect("failed to create temporary file");
+    for line in lines {
+        writeln!(file, "{}", line).expect("failed to write temporary file");
+    }
+    let path = file.path().to_str().expect("temporary path is not UTF-8");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_preg"))
+        .env("PREG_CONFIG_PATH", "")
+        .args(["-f", path, "--color", "never"])
+        .args(args)
+        .output()
+        .expect("failed to run preg");
+    assert!(output.status.success(), "preg {:?} failed", args);
+    String::from_utf8(output.stdout).expect("output is not UTF-8")
+}
+
+/// The line numbers printed with `-n`.
+fn line_numbers(output: &str) -> BTreeSet<usize> {
+    output
+        .lines()
+        .map(|line| {
+            let (number, _) = line.split_once(':').expect("missing line number");
+            number.parse().expect("line number is not a number")
+        })
+        .collect()
+}
+
+fn lines() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec("[abAB -]{0,12}", 0..20)
+}
+
+fn pattern() -> impl Strategy<Value = String> {
+    "[abAB]{1,3}".prop_map(String::from)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn invert_is_the_complement(lines in lines(), pattern in pattern()) {
+        let matching = line_numbers(&run(&lines, &["-p", &pattern, "-n"]));
+        let rest = line_numbers(&run(&lines, &["-p", &pattern, "-n", "-v"]));
+
+        prop_assert!(matching.is_disjoint(&rest));
+        let all: BTreeSet<usize> = (1..=lines.len()).collect();
+        prop_assert_eq!(matching.union(&rest).copied().collect::<BTreeSet<_>>(), all);
+    }
+
+    #[test]
+    fn count_equals_printed_lines(lines in lines(), pattern in pattern(), invert in any::<bool>()) {
+        let mut args = vec!["-p", &pattern];
+        if invert {
+            args.push("-v");
+        }
+        let printed = run(&lines, &args).lines().count();
+        args.push("-c");
+        let counted = run(&lines, &args);
+        prop_assert_eq!(counted.trim(), printed.to_string());
+    }
+
+    #[test]
+    fn count_matches_equals_only_matching(lines in lines(), pattern in pattern()) {
+        let printed = run(&lines, &["-p", &pattern, "-o"]).lines().count();
+        let counted = run(&lines, &["-p", &pattern, "--count-matches"]);
+        prop_assert_eq!(counted.trim(), printed.to_string());
+    }
+
+    #[test]
+    fn ignore_case_only_adds_lines(lines in lines(), pattern in pattern()) {
+        let exact = line_numbers(&run(&lines, &["-p", &pattern, "-n"]));
+        let folded = line_numbers(&run(&lines, &["-p", &pattern, "-n", "-i"]));
+        prop_assert!(exact.is_subset(&folded));
+    }
+
+    #[test]
+    fn passthru_reproduces_the_input(lines in lines(), pattern in pattern()) {
+        let expected: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        prop_assert_eq!(run(&lines, &["-p", &pattern, "--passthru"]), expected);
+    }
+}